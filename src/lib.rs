@@ -1,7 +1,9 @@
 use std::{
+    env,
+    path::PathBuf,
     sync::{
         Arc, Mutex,
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
     },
     thread,
     time::{Duration, Instant},
@@ -18,7 +20,10 @@ use scap::{
 use sysinfo::{ProcessRefreshKind, RefreshKind, System};
 
 use crate::utils::{
+    cast::PERFECT_CAST,
+    catch::Catch,
     colors::ColorTarget,
+    cycle::{FishingState, StateMetrics},
     geometry::{Dimensions, Point, Region},
 };
 
@@ -48,8 +53,33 @@ pub fn check_running(name: &str) -> bool {
         .any(|process| process.name() == name)
 }
 
+/// Returns where to keep a file between sessions, in the user cache directory
+#[must_use]
+pub fn cache_path(name: &str) -> PathBuf {
+    let directory = if cfg!(target_os = "windows") {
+        env::var_os("LOCALAPPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+    }
+    .unwrap_or_else(env::temp_dir)
+    .join("fischy");
+
+    if let Err(e) = std::fs::create_dir_all(&directory) {
+        warn!(
+            "Couldn't create the cache directory {}: {e}",
+            directory.display()
+        );
+    }
+
+    directory.join(name)
+}
+
 pub struct ScreenRecorder {
     old_frame: Arc<Mutex<Frame>>,
+    /// Incremented each time a new frame is stored
+    sequence: Arc<AtomicU64>,
 
     pub dimensions: Dimensions,
 }
@@ -112,24 +142,53 @@ impl ScreenRecorder {
             .get_next_frame()
             .map_err(|e| format!("Can't receive frames: {e}"))?;
         let old_frame = Arc::new(Mutex::new(first_frame));
+        let sequence = Arc::new(AtomicU64::new(0));
 
         // We have to create a thread that consume all our frames to prevent a memory explosion
         let frame_clone = Arc::clone(&old_frame);
+        let sequence_clone = Arc::clone(&sequence);
         thread::spawn(move || {
             while let Ok(frame) = capturer.get_next_frame() {
                 // Try to store the latest frame
                 if let Ok(mut guard) = frame_clone.try_lock() {
                     *guard = frame;
+                    sequence_clone.fetch_add(1, Ordering::Release);
                 }
             }
         });
 
         Ok(Self {
             old_frame,
+            sequence,
             dimensions: Dimensions { width, height },
         })
     }
 
+    /// Returns the number of the latest frame stored
+    #[must_use]
+    pub fn sequence(&self) -> u64 {
+        self.sequence.load(Ordering::Acquire)
+    }
+
+    /// Wait until a frame newer than the frame `sequence` is stored.
+    /// Take `sequence` right after an input to get a frame showing its effect
+    pub fn wait_frame_after(&self, sequence: u64, cond: &AtomicBool) {
+        let start = Instant::now();
+
+        // Don't wait forever if the capture stalled
+        while self.sequence() <= sequence
+            && start.elapsed() < Duration::from_millis(500)
+            && !cond.load(Ordering::Relaxed)
+        {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Wait until a frame newer than the current one is stored
+    pub fn wait_fresh_frame(&self, cond: &AtomicBool) {
+        self.wait_frame_after(self.sequence(), cond);
+    }
+
     fn take_frame(&mut self) -> Result<Frame, String> {
         match self.old_frame.lock() {
             Ok(f) => Ok(f.clone()),
@@ -209,21 +268,22 @@ impl Region {
         Xs: IntoIterator<Item = u32>,
         Ys: IntoIterator<Item = u32> + Clone,
     {
+        let targets = targets.iter().map(ColorTarget::prepare).collect::<Vec<_>>();
         xs.into_iter()
             .flat_map(|x| ys.clone().into_iter().map(move |y| (x, y)))
             .find(|&(x, y)| targets.iter().any(|t| t.matches(screen.get_pixel(x, y))))
             .map(|(x, y)| Point { x, y })
     }
 
-    /// Search a color in the middle row, left to right
+    /// Search a color in the row `y`, left to right
     #[must_use]
-    pub fn search_color_mid_ltr(
+    pub fn search_color_row_ltr(
         &self,
         screen: &RgbImage,
         targets: &[ColorTarget],
+        y: u32,
     ) -> Option<Point> {
-        let [x_min, y_min, x_max, y_max] = self.corners();
-        let y = y_min.midpoint(y_max);
+        let [x_min, _, x_max, _] = self.corners();
         Self::search_color_impl(screen, targets, x_min..=x_max, &[y])
     }
 
@@ -266,6 +326,16 @@ pub struct Stats {
     pub enabled: bool,
     /// Shake count
     pub shakes: Box<u64>,
+    /// Shakes skipped because the bubble was already hit
+    pub duplicate_shakes: Box<u64>,
+    /// Maximum shake count during a single cast
+    max_cast_shakes: Box<u64>,
+    /// Casts released by watching the power bar
+    measured_casts: Box<u64>,
+    /// Casts with a perfect power
+    perfect_casts: Box<u64>,
+    /// Sum of the power of measured casts
+    total_cast_power: Box<u64>,
     /// Reel count
     pub reels: Box<u64>,
     /// Fish count
@@ -276,6 +346,22 @@ pub struct Stats {
     max_fishing_time: Box<u64>,
     /// Minimum fishing time in seconds
     min_fishing_time: Box<u64>,
+    /// Time spent in each state of the fishing cycle
+    states: Vec<(FishingState, StateMetrics)>,
+    /// UI checks done during the session
+    ui_checks: Box<u64>,
+    /// Overlays dismissed or seen by the UI checks
+    dismissed_overlays: Box<u64>,
+    /// Times each overlay appeared
+    overlays: Vec<(&'static str, u64)>,
+    /// Fishes read from the catch notification, in order
+    catches: Vec<Catch>,
+    /// Times the inventory was full
+    pub inventory_full: Box<u64>,
+    /// Times the character died or got moved
+    pub scene_changes: Box<u64>,
+    /// Walks back to the fishing spot
+    pub drift_corrections: Box<u64>,
 }
 
 impl Stats {
@@ -285,15 +371,43 @@ impl Stats {
             enabled,
             reels: Box::new(0),
             shakes: Box::new(0),
+            duplicate_shakes: Box::new(0),
+            max_cast_shakes: Box::new(0),
+            measured_casts: Box::new(0),
+            perfect_casts: Box::new(0),
+            total_cast_power: Box::new(0),
             fishes: Box::new(0),
             total_fishing_time: Box::new(0),
             max_fishing_time: Box::new(u64::MIN),
             min_fishing_time: Box::new(u64::MAX),
+            states: Vec::new(),
+            ui_checks: Box::new(0),
+            dismissed_overlays: Box::new(0),
+            overlays: Vec::new(),
+            catches: Vec::new(),
+            inventory_full: Box::new(0),
+            scene_changes: Box::new(0),
+            drift_corrections: Box::new(0),
         }
     }
 
     fn print_stats(self) {
         println!("Shake count: {}", self.shakes);
+        if let Some(average) = self.shakes.checked_div(*self.reels) {
+            println!(
+                "Average shakes per cast: {average} (maximum was {})",
+                self.max_cast_shakes
+            );
+        }
+        println!("Duplicate shakes avoided: {}", self.duplicate_shakes);
+        if let Some(average) = self.total_cast_power.checked_div(*self.measured_casts) {
+            println!(
+                "Perfect casts: {}/{} (average power was {average}%, {} casts were timed)",
+                self.perfect_casts,
+                self.measured_casts,
+                *self.reels - *self.measured_casts
+            );
+        }
         println!("Reels tries count: {}", self.reels);
         println!("Missed reels count: {}", *self.reels - *self.fishes);
         println!("Fishes count: {}", self.fishes);
@@ -305,6 +419,37 @@ impl Stats {
                 self.min_fishing_time
             );
         }
+        println!(
+            "UI checks: {} ({} overlays handled)",
+            self.ui_checks, self.dismissed_overlays
+        );
+        for (name, count) in &self.overlays {
+            println!("  - {name}: {count} times");
+        }
+        println!("Inventory full: {} times", self.inventory_full);
+        println!("Character moved: {} times", self.scene_changes);
+        println!("Drift corrections: {}", self.drift_corrections);
+        println!("Identified catches: {}", self.catches.len());
+        for catch in &self.catches {
+            let mut line = format!("  - {}", catch.name);
+            if let Some(mutation) = &catch.mutation {
+                line = format!("{line} ({mutation})");
+            }
+            if let Some(weight) = catch.weight {
+                line = format!("{line}, {weight}kg");
+            }
+            if let Some(rarity) = catch.rarity {
+                line = format!("{line}, {rarity:?}");
+            }
+            println!("{line}");
+        }
+        for (state, metrics) in &self.states {
+            println!(
+                "{state:?}: entered {} times, {}s total",
+                metrics.visits,
+                metrics.time.as_secs()
+            );
+        }
     }
 
     pub fn print(self) {
@@ -321,6 +466,56 @@ impl Stats {
             *self.min_fishing_time = (*self.min_fishing_time).min(time);
         }
     }
+
+    pub fn add_reel(&mut self) {
+        if self.enabled {
+            *self.reels += 1;
+        }
+    }
+
+    pub fn add_cast(&mut self, power: Option<u8>) {
+        if let Some(power) = power {
+            *self.measured_casts += 1;
+            *self.total_cast_power += u64::from(power);
+            if power >= PERFECT_CAST {
+                *self.perfect_casts += 1;
+            }
+        }
+    }
+
+    pub fn add_cast_shakes(&mut self, shakes: u64) {
+        *self.max_cast_shakes = (*self.max_cast_shakes).max(shakes);
+    }
+
+    pub fn add_ui_check(&mut self, dismissed: &[&'static str]) {
+        *self.ui_checks += 1;
+        *self.dismissed_overlays += dismissed.len() as u64;
+        for name in dismissed {
+            match self.overlays.iter_mut().find(|(n, _)| n == name) {
+                Some((_, count)) => *count += 1,
+                None => self.overlays.push((name, 1)),
+            }
+        }
+    }
+
+    pub fn record_catch(&mut self, catch: Catch) {
+        self.catches.push(catch);
+    }
+
+    pub fn record_state(&mut self, state: FishingState, time: Duration) {
+        let idx = self
+            .states
+            .iter()
+            .position(|(s, _)| *s == state)
+            .unwrap_or_else(|| {
+                self.states.push((state, StateMetrics::default()));
+                self.states.len() - 1
+            });
+
+        let metrics = &mut self.states[idx].1;
+        metrics.visits += 1;
+        metrics.time += time;
+    }
 }
 
 /// Sleep `n` millis with random jitter in millis
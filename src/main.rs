@@ -1,4 +1,4 @@
-use std::ops::AddAssign;
+use std::fs;
 use std::process::exit;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
@@ -13,17 +13,32 @@ use enigo::{
     Enigo, Mouse, Settings,
 };
 use fischy::utils::{
-    args::rod_position_parser,
-    checks::{chat_check, quest_check, scoreboard_check, server_alive_check, treasure_maps_check},
+    args::{
+        InventoryPolicy, KeepAlive, ShakeMode, color_parser, percentage_parser, rod_position_parser,
+    },
+    cast::{PERFECT_CAST, PowerBar},
+    catch::Catch,
+    checks::{
+        CHAT, IDLE_WARNING, INVENTORY_FULL, POPUPS, QUEST, SCOREBOARD, SERVER_OFFLINE,
+        TREASURE_MAPS,
+    },
     clickers::{appraise_items, fetch_crab_cages, place_crab_cages, sell_items, summon_totem},
-    colors::ColorTarget,
+    cycle::{Angler, FishingCycle, Timeouts},
     fishing::Rod,
-    fishing::{MiniGame, Move},
-    geometry::{Dimensions, Point, Region},
+    fishing::{FishPosition, MiniGame, Move},
+    geometry::{Point, Region},
     helpers::BadCast,
+    hotbar::Hotbar,
+    inventory,
+    ocr::{self, LEARNED_GLYPHS},
+    overlays::{OverlayWatch, dismiss_overlays},
+    reconnect::{Backoff, DisconnectPopup, retry},
+    scene::{DriftWatch, Scene, SceneChange, SceneWatch, Walk},
+    shake::{Bubble, MIN_BUBBLE_SCORE, search_bubbles},
+    viewpoint::{View, ViewIssue, ViewProfile, WATER},
 };
 use fischy::{
-    ScreenRecorder, Scroller, Stats, check_running, get_roblox_executable_name, sleep,
+    ScreenRecorder, Scroller, Stats, cache_path, check_running, get_roblox_executable_name, sleep,
     sleep_with_jitter,
 };
 use image::{Rgb, RgbImage};
@@ -32,6 +47,36 @@ use rdev::{Event, EventType::KeyPress, Key, listen, simulate};
 use window_raiser::raise;
 
 static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+static RESUME: AtomicBool = AtomicBool::new(false);
+/// Set while jumping, so `<SPACE>` doesn't close the macro
+static JUMPING: AtomicBool = AtomicBool::new(false);
+
+/// Where the refined minigame area is kept between sessions, in the cache directory
+const MINI_GAME_CACHE: &str = "mini_game.txt";
+
+/// Camera adjustments before giving up on the setup
+const MAX_CAMERA_ADJUSTMENTS: u8 = 4;
+
+/// Frames without any bubble before the shake phase is considered over
+const NAVIGATION_END_FRAMES: u8 = 3;
+
+/// Presses on the same bubble before toggling the UI navigation
+const MAX_STUCK_PRESSES: u8 = 3;
+
+/// UI navigation toggles before giving up on the keyboard
+const MAX_NAVIGATION_TOGGLES: u8 = 2;
+
+/// Frames in a row showing the full inventory before reacting to it
+const INVENTORY_FULL_FRAMES: u8 = 3;
+
+/// Frames in a row without the rod highlighted before selecting it
+const ROD_MISSING_FRAMES: u8 = 3;
+
+/// Frames between two searches for popups while shaking, reading them is slow
+const POPUP_CHECK_FRAMES: u8 = 5;
+
+/// Longest wait between two reconnection tries
+const MAX_RECONNECT_DELAY: Duration = Duration::from_mins(5);
 
 #[derive(Parser)]
 #[command(
@@ -45,18 +90,18 @@ To make this program work:
 )]
 #[allow(clippy::struct_excessive_bools)]
 struct Args {
-    /// Sleep between shakes to prevent capturing the cursor
-    #[arg(long, default_value_t = 400)]
-    lag: u32,
-
     /// Maximum shake count
     #[arg(long, default_value_t = 40)]
     max_shake_count: u8,
 
-    /// Do only the shake part
+    /// Only cast and shake, the reel mini-game is left to the player
     #[arg(short, long, default_value_t = false)]
     shake_only: bool,
 
+    /// How to shake, navigation requires the in-game "navigation" shake mode
+    #[arg(long, value_enum, default_value_t = ShakeMode::Click)]
+    shake_mode: ShakeMode,
+
     /// Don't print stats
     #[arg(long, default_value_t = false)]
     no_stats: bool,
@@ -65,9 +110,41 @@ struct Args {
     #[arg(long)]
     no_camera_setup: bool,
 
-    /// Placement of the fishing rod in the hotbar
-    #[arg(long, default_value_t = 1, value_parser = rod_position_parser)]
-    rod_position_hotbar: u16,
+    /// Water color checked by the camera setup, as `RRGGBB`, for places with unusual water
+    #[arg(long, value_parser = color_parser)]
+    water_color: Option<Rgb<u8>>,
+
+    /// Placement of the fishing rod in the hotbar, found from the held item by default
+    #[arg(long, value_parser = rod_position_parser)]
+    rod_position_hotbar: Option<u16>,
+
+    /// Reconnect when the server shuts down, instead of exiting
+    #[arg(long, default_value_t = false)]
+    reconnect: bool,
+
+    /// Maximum reconnection tries before exiting
+    #[arg(long, default_value_t = 5)]
+    max_reconnect_tries: u8,
+
+    /// Input sent between casts, so Roblox doesn't kick us for being idle
+    #[arg(long, value_enum, default_value_t = KeepAlive::Nudge)]
+    keep_alive: KeepAlive,
+
+    /// Steps allowed to walk back to the fishing spot after each drift, the macro stops past it
+    #[arg(long, default_value_t = 20)]
+    max_drift_corrections: u16,
+
+    /// What to do when the inventory is full
+    #[arg(long, value_enum, default_value_t = InventoryPolicy::Pause)]
+    inventory_full: InventoryPolicy,
+
+    /// Command selling the inventory, run by the shell with the `sell` policy
+    #[arg(long, required_if_eq("inventory_full", "sell"))]
+    sell_command: Option<String>,
+
+    /// Power bar fill at which the cast is released, in percent
+    #[arg(long, default_value_t = PERFECT_CAST, value_parser = percentage_parser)]
+    cast_power: u8,
 
     /// Change reaction time, in milliseconds
     #[arg(long, default_value_t = 50)]
@@ -101,6 +178,11 @@ struct Args {
     /// if forced (set to true), then confirmation will be automatic (good for Shiny and Sparkling)
     #[arg(short('a'), long, num_args(0..=1), default_missing_value = "false")]
     appraise_items: Option<bool>,
+
+    /// Learn the glyphs of the game font from a screenshot crop of a single line of text,
+    /// knowing what it says (`--learn-glyphs level_up.png "Level Up!"`)
+    #[arg(long, num_args(2), value_names = ["CROP", "TEXT"])]
+    learn_glyphs: Option<Vec<String>>,
 }
 
 /// Init logger based on verbose option
@@ -118,6 +200,11 @@ fn pre_init() -> Args {
     let args = Args::parse();
     init_logger(args.verbose);
 
+    if let Some([crop, text]) = args.learn_glyphs.as_deref() {
+        learn_glyphs(crop, text);
+        exit(0);
+    }
+
     info!("Starting Roblox Fishing Macro");
     if args.verbose {
         info!("Debug mode enabled");
@@ -144,6 +231,96 @@ fn pre_init() -> Args {
     args
 }
 
+/// Learn the glyphs of a text crop, they are used by the OCR from then on
+fn learn_glyphs(crop: &str, text: &str) {
+    let image = image::open(crop)
+        .expect("Couldn't open the text crop")
+        .to_rgb8();
+    let region = Region {
+        point1: Point { x: 0, y: 0 },
+        point2: Point {
+            x: image.width() - 1,
+            y: image.height() - 1,
+        },
+    };
+
+    let glyphs = ocr::learn_glyphs(&image, &region, text).unwrap_or_else(|e| {
+        warn!("Couldn't learn the glyphs: {e}");
+        exit(1);
+    });
+    let path = cache_path(LEARNED_GLYPHS);
+    let learned = fs::read_to_string(&path).unwrap_or_default();
+    fs::write(&path, learned + &glyphs).expect("Couldn't save the learned glyphs");
+    info!("Learned the glyphs of {text:?} in {}", path.display());
+}
+
+/// Find where the rod is in the hotbar, it is supposed to be held when starting
+fn find_rod_slot(args: &Args, hotbar: Option<&Hotbar>, screen: &RgbImage) -> u16 {
+    let rod_slot = args
+        .rod_position_hotbar
+        .or_else(|| hotbar.and_then(|h| h.selected(screen)))
+        .unwrap_or_else(|| {
+            warn!("Couldn't find the rod in the hotbar, assuming it is in the first slot");
+            1
+        });
+    info!("Rod is in the hotbar slot {rod_slot}");
+
+    rod_slot
+}
+
+/// Run the requested tool instead of fishing, then exit
+fn run_tools(args: &Args, enigo: &mut Enigo, safe_point: &Point, recorder: &mut ScreenRecorder) {
+    if let Some(clicks) = args.place_crab_cages {
+        place_crab_cages(enigo, safe_point, clicks, &SHUTDOWN);
+        exit(0);
+    }
+
+    if let Some(cages) = args.fetch_crab_cages {
+        fetch_crab_cages(enigo, safe_point, cages, &SHUTDOWN);
+        exit(0);
+    }
+
+    if let Some(totems) = args.summon_totem {
+        summon_totem(enigo, safe_point, totems, &SHUTDOWN);
+        exit(0);
+    }
+
+    if let Some(items) = args.sell_items {
+        sell_items(enigo, safe_point, items, recorder, &SHUTDOWN);
+        exit(0);
+    }
+
+    if let Some(deactivate_user_confirmation) = args.appraise_items {
+        appraise_items(
+            enigo,
+            safe_point,
+            recorder,
+            &SHUTDOWN,
+            deactivate_user_confirmation,
+        );
+        exit(0);
+    }
+}
+
+/// Draw the computed regions, for debugging purposes
+#[cfg(feature = "imageproc")]
+fn draw_regions(screen: RgbImage, mini_game: &Region, shake_region: &Region, safe_point: &Point) {
+    use fischy::utils::debug::Drawable;
+    use std::sync::Arc;
+
+    let screen = Arc::new(screen);
+
+    mini_game
+        .clone()
+        .draw_async(screen.clone(), "mini_game.png", true);
+    shake_region
+        .clone()
+        .draw_async(screen.clone(), "shake_region.png", true);
+    safe_point
+        .clone()
+        .draw_async(screen, "safe_point.png", true);
+}
+
 fn main() {
     let args = pre_init();
 
@@ -168,181 +345,500 @@ fn main() {
 
     let roblox_button_position = recorder.dimensions.find_roblox_button(&screen);
 
-    scoreboard_check(&mut enigo, &screen);
-    if let Some(p) = roblox_button_position.as_ref() {
-        // Quest check after chat check, because chat window moves the arrow
-        chat_check(&mut enigo, &screen, p, &SHUTDOWN);
-        quest_check(&mut enigo, &screen, p, &SHUTDOWN);
-    }
+    // The rod is supposed to be held when starting
+    let hotbar = Hotbar::search(&screen);
+    let rod_slot = find_rod_slot(&args, hotbar.as_ref(), &screen);
+
+    dismiss_overlays(
+        &mut enigo,
+        &screen,
+        roblox_button_position.as_ref(),
+        &[SCOREBOARD, CHAT, QUEST],
+        &SHUTDOWN,
+    );
 
     let mut mini_game_region = recorder.dimensions.calculate_mini_game_region();
+    if mini_game_region.restore(cache_path(MINI_GAME_CACHE), &recorder.dimensions) {
+        info!("Restored minigame structure from the previous session");
+    }
     let shake_region = recorder
         .dimensions
-        .calculate_shake_region(roblox_button_position);
+        .calculate_shake_region(roblox_button_position.clone());
     let safe_point = recorder
         .dimensions
         .calculate_safe_point(&vec![&mini_game_region, &shake_region])
         .expect("Couldn't find any safe point, no region found.");
 
     #[cfg(feature = "imageproc")]
-    {
-        use fischy::utils::debug::Drawable;
-        use std::sync::Arc;
+    draw_regions(screen, &mini_game_region, &shake_region, &safe_point);
 
-        let screen = Arc::new(screen);
+    run_tools(&args, &mut enigo, &safe_point, &mut recorder);
 
-        mini_game_region
-            .clone()
-            .draw_async(screen.clone(), "mini_game.png", true);
-        shake_region
-            .clone()
-            .draw_async(screen.clone(), "shake_region.png", true);
-        safe_point
-            .clone()
-            .draw_async(screen, "safe_point.png", true);
-    }
+    let mut stats = Stats::new(!args.no_stats);
 
-    if let Some(clicks) = args.place_crab_cages {
-        place_crab_cages(&mut enigo, &safe_point, clicks, &SHUTDOWN);
-        exit(0);
+    if !args.no_camera_setup
+        && let Err(e) = initialize_viewpoint(
+            &mut enigo,
+            &mut recorder,
+            &mini_game_region,
+            &args,
+            &SHUTDOWN,
+        )
+    {
+        warn!("Camera setup doesn't look right: {e}");
     }
 
-    if let Some(cages) = args.fetch_crab_cages {
-        fetch_crab_cages(&mut enigo, &safe_point, cages, &SHUTDOWN);
-        exit(0);
-    }
+    // Reference of the fishing spot
+    recorder.wait_fresh_frame(&SHUTDOWN);
+    let scene = Scene::capture(
+        &recorder
+            .take_screenshot()
+            .expect("Couldn't take screenshot"),
+    );
 
-    if let Some(totems) = args.summon_totem {
-        summon_totem(&mut enigo, &safe_point, totems, &SHUTDOWN);
-        exit(0);
-    }
+    let mut session = Session {
+        enigo: &mut enigo,
+        recorder: &mut recorder,
+        safe_point: &safe_point,
+        mini_game: &mut mini_game_region,
+        shake_region,
+        args: &args,
+        roblox_button: roblox_button_position,
+        hotbar,
+        rod_slot,
+        popups: OverlayWatch::new(POPUPS, POPUP_CHECK_FRAMES),
+        stale_frame: 0,
+        keyboard_shakes: args.shake_mode == ShakeMode::Navigation,
+        scene: SceneWatch::new(scene),
+        drift: DriftWatch::new(args.max_drift_corrections),
+        previous_hook_x: 0,
+        last_move: None,
+    };
+    FishingCycle::new(Timeouts {
+        max_shake_count: args.max_shake_count,
+        ..Default::default()
+    })
+    .run(&mut session, &mut stats, &SHUTDOWN);
 
-    if let Some(items) = args.sell_items {
-        sell_items(&mut enigo, &safe_point, items, &mut recorder, &SHUTDOWN);
-        exit(0);
+    stats.print();
+}
+
+/// Everything needed to fish in the current session
+struct Session<'a> {
+    enigo: &'a mut Enigo,
+    recorder: &'a mut ScreenRecorder,
+    safe_point: &'a Point,
+    mini_game: &'a mut MiniGame,
+    /// Placed from the Roblox button
+    shake_region: Region,
+    args: &'a Args,
+
+    /// Roblox button position, if found
+    roblox_button: Option<Point>,
+    /// Hotbar, if found
+    hotbar: Option<Hotbar>,
+    /// Hotbar slot holding the rod
+    rod_slot: u16,
+
+    /// Popups seen while shaking
+    popups: OverlayWatch,
+    /// Latest frame already searched for shakes, or taken before our last input
+    stale_frame: u64,
+    /// If the shake bubbles are hit with the UI navigation
+    keyboard_shakes: bool,
+
+    /// View of the fishing spot
+    scene: SceneWatch,
+    /// Walking back to the fishing spot
+    drift: DriftWatch,
+
+    /// Hook position during the previous reel step
+    previous_hook_x: i32,
+    /// Last movement of the hook, repeated when the frame is too noisy
+    last_move: Option<Move>,
+}
+
+impl Session<'_> {
+    /// Reconnect if the disconnection popup is shown, or stop without `--reconnect`
+    ///
+    /// # Return
+    /// If the server was offline
+    fn server_offline(&mut self, screen: &RgbImage) -> bool {
+        if SERVER_OFFLINE.detect(screen, None).is_none() {
+            return false;
+        }
+
+        warn!("Disconnected from the server");
+        if self.args.reconnect {
+            self.reconnect();
+        } else {
+            info!("Closing due to {}...", SERVER_OFFLINE.name);
+            SHUTDOWN.store(true, Ordering::Relaxed);
+        }
+
+        true
     }
 
-    if let Some(deactivate_user_confirmation) = args.appraise_items {
-        appraise_items(
-            &mut enigo,
-            &safe_point,
-            &mut recorder,
+    /// Click the reconnect button until the game is back, exits after too many tries
+    fn reconnect(&mut self) {
+        let mut backoff = Backoff::new(
+            Duration::from_secs(5),
+            MAX_RECONNECT_DELAY,
+            self.args.max_reconnect_tries,
+        );
+        let reconnected = retry(
+            &mut backoff,
+            || {
+                info!("Reconnecting...");
+                self.click_reconnect() && self.wait_reload()
+            },
             &SHUTDOWN,
-            deactivate_user_confirmation,
         );
-        exit(0);
+
+        if reconnected {
+            info!("Reconnected");
+            self.restart();
+        } else if !SHUTDOWN.load(Ordering::Relaxed) {
+            warn!("Couldn't reconnect after {} tries", backoff.tries());
+            SHUTDOWN.store(true, Ordering::Relaxed);
+        }
     }
 
-    let mut stats = Stats::new(!args.no_stats);
+    /// Click the reconnect button of the disconnection popup
+    ///
+    /// # Return
+    /// If the button was found
+    fn click_reconnect(&mut self) -> bool {
+        let screen = self
+            .recorder
+            .take_screenshot()
+            .expect("Couldn't take screenshot");
+        let Some(popup) = DisconnectPopup::search(&screen) else {
+            warn!("Reconnect button not found");
+            return false;
+        };
 
-    if !args.no_camera_setup {
-        initialize_viewpoint(&mut enigo, &recorder.dimensions, &SHUTDOWN);
+        if let Some(leave) = popup.leave {
+            info!("Leave button found ({}% match)", leave.score);
+        }
+        let button = &popup.reconnect.center;
+        self.enigo
+            .move_mouse_ig_abs(button.x.cast_signed(), button.y.cast_signed())
+            .expect("Couldn't move mouse to the reconnect button");
+        sleep(Duration::from_millis(100), &SHUTDOWN); // we may move the mouse too fast
+        self.enigo
+            .button(Button::Left, Click)
+            .expect("Couldn't click on the reconnect button");
+
+        true
     }
 
-    macro_loop(
-        &mut enigo,
-        &mut recorder,
-        &safe_point,
-        &mut mini_game_region,
-        &shake_region,
-        &args,
-        &mut stats,
-    );
+    /// Wait for the game to be loaded again
+    fn wait_reload(&mut self) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_secs(90) && !SHUTDOWN.load(Ordering::Relaxed) {
+            sleep(Duration::from_secs(2), &SHUTDOWN);
+            let screen = self
+                .recorder
+                .take_screenshot()
+                .expect("Couldn't take screenshot");
 
-    stats.print();
-}
+            if SERVER_OFFLINE.detect(&screen, None).is_none()
+                && let Some(button) = self.recorder.dimensions.find_roblox_button(&screen)
+            {
+                // Let the game finish loading
+                sleep(Duration::from_secs(5), &SHUTDOWN);
+                self.roblox_button = Some(button);
+                return true;
+            }
+        }
 
-/// Shake the rod and catch fishes
-fn macro_loop(
-    enigo: &mut Enigo,
-    recorder: &mut ScreenRecorder,
-    safe_point: &Point,
-    mini_game: &mut MiniGame,
-    shake_region: &Region,
-    args: &Args,
-    stats: &mut Stats,
-) {
-    let mut last_shake_time = Instant::now();
-    let mut shake_count = 0;
-    let mut tries_fishing = 0;
-
-    // Initial reel
-    reels(
-        enigo,
-        &mut last_shake_time,
-        &mut shake_count,
-        safe_point,
-        stats,
-    );
+        false
+    }
 
-    while !SHUTDOWN.load(Ordering::Relaxed) {
-        // Check for shake
-        if let Some((Point { x, y }, image)) =
-            check_shake(enigo, recorder, shake_region, safe_point, args)
-            && server_alive_check(&image, &SHUTDOWN)
+    /// Press `Return` on the bubbles selected by the UI navigation, until the shake UI disappears
+    ///
+    /// # Return
+    /// Number of bubbles hit, or None if the UI navigation doesn't select them
+    fn navigate_shakes(&mut self, bubble: &Bubble) -> Option<u64> {
+        let start = Instant::now();
+        let mut current = bubble.clone();
+        let (mut hits, mut missing, mut stuck, mut toggles) = (0, 0, 0, 0);
+
+        while missing < NAVIGATION_END_FRAMES
+            && start.elapsed() < Duration::from_secs(10)
+            && !SHUTDOWN.load(Ordering::Relaxed)
         {
-            treasure_maps_check(enigo, &image, &SHUTDOWN);
-
-            // Click at the shake position
-            info!("Shake @ ({x}, {y})");
-            enigo
-                .move_mouse_ig_abs(x.cast_signed(), y.cast_signed())
-                .expect("Failed moving mouse to shake bubble");
-            sleep(Duration::from_millis(100), &SHUTDOWN); // we may move the mouse too fast
-            enigo
-                .button(Button::Left, Click)
-                .expect("Failed clicking to shake bubble");
-
-            stats.shakes.add_assign(1);
-
-            // Too much tries
-            if shake_count > args.max_shake_count {
-                reels(
-                    enigo,
-                    &mut last_shake_time,
-                    &mut shake_count,
-                    safe_point,
-                    stats,
+            if missing == 0 {
+                info!(
+                    "Shake @ ({}, {}) with keyboard",
+                    current.center.x, current.center.y
                 );
+                press_key(Key::Return);
+                self.stale_frame = self.recorder.sequence();
+            }
+
+            self.recorder.wait_frame_after(self.stale_frame, &SHUTDOWN);
+            self.stale_frame = self.recorder.sequence();
+            let screen = self
+                .recorder
+                .take_screenshot()
+                .expect("Couldn't take screenshot");
+            let masks = cursor_mask(self.enigo, &screen)
+                .into_iter()
+                .collect::<Vec<_>>();
+            let Some(next) = search_bubbles(&screen, &self.shake_region, &masks)
+                .into_iter()
+                .next()
+                .filter(|b| b.score >= MIN_BUBBLE_SCORE)
+            else {
+                hits += u64::from(missing == 0);
+                missing += 1;
+                continue;
+            };
+            missing = 0;
+
+            if !next.bounds.contains(&current.center) {
+                hits += 1;
+                stuck = 0;
+                current = next;
                 continue;
             }
 
-            shake_count += 1;
-            last_shake_time = Instant::now();
-            continue;
+            // `Return` does nothing, the UI navigation is off
+            stuck += 1;
+            if stuck >= MAX_STUCK_PRESSES {
+                if toggles >= MAX_NAVIGATION_TOGGLES {
+                    warn!("UI navigation doesn't select the shake bubbles, clicking them instead");
+                    return None;
+                }
+                info!("Toggling the UI navigation");
+                press_key(Key::BackSlash);
+                self.stale_frame = self.recorder.sequence();
+                toggles += 1;
+                stuck = 0;
+            }
         }
 
-        // Timeout
-        if last_shake_time.elapsed() > Duration::from_secs(5) {
-            reels(
-                enigo,
-                &mut last_shake_time,
-                &mut shake_count,
-                safe_point,
-                stats,
-            );
-            continue;
+        if missing < NAVIGATION_END_FRAMES {
+            warn!("The shake phase didn't end");
         }
 
+        Some(hits)
+    }
+
+    /// Move the hook
+    fn steer(&mut self, movement: Move) {
+        match movement {
+            Move::Left => self
+                .enigo
+                .button(Button::Left, Release)
+                .expect("Going left"),
+            Move::Right => self.enigo.button(Button::Left, Press).expect("Going right"),
+            Move::Spam => self
+                .enigo
+                .button(Button::Left, Click)
+                .expect("Clicking failed"),
+        }
+        self.last_move = Some(movement);
+    }
+
+    /// Run the startup checks again, exits when the camera can't be set up
+    fn restart(&mut self) {
+        // The Roblox button has been found again after the reload
+        self.shake_region = self
+            .recorder
+            .dimensions
+            .calculate_shake_region(self.roblox_button.clone());
+        self.tidy_up();
+        if !self.args.no_camera_setup
+            && let Err(e) = initialize_viewpoint(
+                self.enigo,
+                self.recorder,
+                self.mini_game,
+                self.args,
+                &SHUTDOWN,
+            )
+        {
+            // The game moved the character, it may not face any water anymore
+            warn!("Closing because the camera can't be set up again: {e}");
+            SHUTDOWN.store(true, Ordering::Relaxed);
+            return;
+        }
+        self.recover();
+
+        // The fishing spot may have changed
+        self.recorder.wait_fresh_frame(&SHUTDOWN);
+        let screen = self
+            .recorder
+            .take_screenshot()
+            .expect("Couldn't take screenshot");
+        self.scene.scene = Scene::capture(&screen);
+    }
+}
+
+impl Angler for Session<'_> {
+    /// Start the fishing process
+    fn cast(&mut self) -> Option<u8> {
+        self.mini_game.reset_bite();
+
+        // Move mouse
+        self.enigo
+            .move_mouse_ig_abs(
+                self.safe_point.x.cast_signed(),
+                self.safe_point.y.cast_signed(),
+            )
+            .expect("Can't move mouse");
+
+        // Click to be sure we are not shaking
+        self.enigo
+            .button(Button::Left, Click)
+            .expect("Can't click before reel");
+        sleep_with_jitter(70, 10, &SHUTDOWN);
+
+        info!("Reeling...");
+        // Casting motion
+        self.enigo
+            .button(Button::Left, Press)
+            .expect("Can't backswing: failed to press mouse button");
+
+        // Find the power bar while it fills up
+        let region = self.recorder.dimensions.calculate_power_bar_region();
+        self.recorder.wait_fresh_frame(&SHUTDOWN);
+        let before = self
+            .recorder
+            .take_screenshot()
+            .expect("Couldn't take screenshot");
+        sleep(Duration::from_millis(150), &SHUTDOWN);
+        self.recorder.wait_fresh_frame(&SHUTDOWN);
+        let after = self
+            .recorder
+            .take_screenshot()
+            .expect("Couldn't take screenshot");
+
+        let power = if let Some(bar) = PowerBar::search(&before, &after, &region) {
+            let start = Instant::now();
+            let mut power = bar.fill(&after);
+            // Release on the target, or once the meter starts going down
+            while power < self.args.cast_power
+                && start.elapsed() < Duration::from_secs(3)
+                && !SHUTDOWN.load(Ordering::Relaxed)
+            {
+                self.recorder.wait_fresh_frame(&SHUTDOWN);
+                let screen = self
+                    .recorder
+                    .take_screenshot()
+                    .expect("Couldn't take screenshot");
+                let previous = power;
+                power = bar.fill(&screen);
+                if power < previous {
+                    info!("Cast power dropped from {previous}%");
+                    break;
+                }
+            }
+            info!("Cast power is {power}%");
+            Some(power)
+        } else {
+            info!("Power bar not found, timed cast");
+            sleep_with_jitter(750, 300, &SHUTDOWN);
+            None
+        };
+
+        self.enigo
+            .button(Button::Left, Release)
+            .expect("Can't release the line: failed to release mouse button");
+        self.stale_frame = self.recorder.sequence();
+
+        power
+    }
+
+    fn find_shake(&mut self) -> Option<Bubble> {
+        let (bubble, image) = check_shake(
+            self.enigo,
+            self.recorder,
+            &self.shake_region,
+            &mut self.popups,
+            &mut self.stale_frame,
+        );
+        let Some(bubble) = bubble else {
+            // Disconnections stop the shakes, don't wait for the next UI check
+            self.server_offline(&image);
+            return None;
+        };
+
+        dismiss_overlays(self.enigo, &image, None, &[TREASURE_MAPS], &SHUTDOWN);
+
+        Some(bubble)
+    }
+
+    fn shake(&mut self, bubble: &Bubble) -> u64 {
+        if self.keyboard_shakes {
+            if let Some(hits) = self.navigate_shakes(bubble) {
+                return hits;
+            }
+            self.keyboard_shakes = false;
+        }
+
+        // Click at the shake position
+        let bubble = &bubble.center;
+        info!("Shake @ ({}, {})", bubble.x, bubble.y);
+        self.enigo
+            .move_mouse_ig_abs(bubble.x.cast_signed(), bubble.y.cast_signed())
+            .expect("Failed moving mouse to shake bubble");
+        sleep(Duration::from_millis(100), &SHUTDOWN); // we may move the mouse too fast
+        self.enigo
+            .button(Button::Left, Click)
+            .expect("Failed clicking to shake bubble");
+        self.stale_frame = self.recorder.sequence();
+
+        1
+    }
+
+    fn hooked(&mut self) -> bool {
         // Take screenshot for processing
-        let screen = recorder
+        let screen = self
+            .recorder
             .take_screenshot()
             .expect("Failed taking screenshot");
 
-        let is_hooked = mini_game.any_fish_hooked(&screen);
+        let is_hooked = self.mini_game.bite_detected(&screen);
+        if is_hooked {
+            info!(
+                "Bite detected ({}% confidence)",
+                self.mini_game.bite_confidence()
+            );
+        }
 
         // Called during the first fish
-        if mini_game.rod.is_none() && is_hooked {
+        if self.mini_game.rod.is_none() && is_hooked {
             // Wait slide-in animation of the minigame
             sleep(Duration::from_millis(150), &SHUTDOWN);
-            let fresher_screen = recorder
+            let fresher_screen = self
+                .recorder
                 .take_screenshot()
                 .expect("Failed taking screenshot");
-            if let Ok(()) = mini_game.refine_area(&fresher_screen) {
-                info!("Updating minigame structure");
-            } else {
-                info!("Failed updating the minigame structure");
-                continue;
+            if !self.mini_game.verify(&fresher_screen) {
+                warn!("The restored minigame structure doesn't match, refining it again");
+                if let Err(e) = fs::remove_file(cache_path(MINI_GAME_CACHE)) {
+                    warn!("Couldn't remove the minigame structure: {e}");
+                }
+            }
+            if !self.mini_game.refined {
+                match self.mini_game.refine_area(&fresher_screen) {
+                    Ok(confidence) => {
+                        info!("Updating minigame structure ({confidence}% confidence)");
+                        if let Err(e) = self
+                            .mini_game
+                            .save(cache_path(MINI_GAME_CACHE), &self.recorder.dimensions)
+                        {
+                            warn!("Couldn't save the minigame structure: {e}");
+                        }
+                    }
+                    Err(e) => {
+                        info!("Failed updating the minigame structure: {e}");
+                        return false;
+                    }
+                }
             }
 
             #[cfg(feature = "imageproc")]
@@ -350,96 +846,73 @@ fn macro_loop(
                 use fischy::utils::debug::Drawable;
                 use std::sync::Arc;
 
-                mini_game.clone().draw_async(
+                self.mini_game.clone().draw_async(
                     Arc::new(fresher_screen.clone()),
                     "mini_game_refined.png",
                     true,
                 );
             }
 
-            mini_game.initialize_rod(Rod::new(&fresher_screen, mini_game));
+            let rod = Rod::new(&fresher_screen, self.mini_game);
+            self.mini_game.initialize_rod(rod);
         }
 
-        // When one fish is on the hook
-        if is_hooked {
-            // Main fishing logic loop
-            info!("Fishing...");
-            fishing_loop(enigo, recorder, mini_game, args, stats);
-            info!("Fishing ended!");
-
-            // After fishing interaction, reel again
-            sleep_with_jitter(2000, 100, &SHUTDOWN);
-            reels(
-                enigo,
-                &mut last_shake_time,
-                &mut shake_count,
-                safe_point,
-                stats,
-            );
-            tries_fishing = 0;
-            continue;
-        } else if tries_fishing >= 10 {
-            tries_fishing = 0;
-            reselect_rod(args).expect("Couldn't select the rod");
-            continue;
-        }
-
-        tries_fishing += 1;
+        is_hooked
     }
-}
-
-/// Select the rod from the hotbar
-/// FIXME: This doesn't work properly on all platforms (Wayland)
-fn reselect_rod(args: &Args) -> Result<(), rdev::SimulateError> {
-    simulate(&rdev::EventType::KeyPress(
-        match args.rod_position_hotbar {
-            1 => Ok(Key::Num1),
-            2 => Ok(Key::Num2),
-            3 => Ok(Key::Num3),
-            4 => Ok(Key::Num4),
-            5 => Ok(Key::Num5),
-            6 => Ok(Key::Num6),
-            7 => Ok(Key::Num7),
-            8 => Ok(Key::Num8),
-            9 => Ok(Key::Num9),
-            _ => Err(()),
-        }
-        .expect("Unkown requested key"),
-    ))
-}
 
-/// Catch a fish!
-fn fishing_loop(
-    enigo: &mut Enigo,
-    recorder: &mut ScreenRecorder,
-    mini_game: &mut MiniGame,
-    args: &Args,
-    stats: &mut Stats,
-) {
-    let fishing_time = Instant::now();
-    let mut previous_hook_x = 0;
-    while !SHUTDOWN.load(Ordering::Relaxed) {
-        let screen = recorder
+    /// Catch a fish!
+    fn reel(&mut self) -> bool {
+        let screen = self
+            .recorder
             .take_screenshot()
             .expect("Couldn't take screenshot");
 
-        let hook = mini_game.find_hook(&screen);
-
-        // Get current fish position
-        let fish_x = if hook.fish_on
-            && let Some(Point { x, .. }) = mini_game.get_fish(&screen)
-        {
-            if args.shake_only {
-                continue;
+        // A splash can hide the hook or the fish, only the bar tells if the bite is over
+        if !self.mini_game.bite_detected(&screen) {
+            info!("The bite is over");
+            if !self.args.shake_only {
+                self.enigo
+                    .button(Button::Left, Release)
+                    .expect("Packup the rod");
             }
-            x.cast_signed()
+            self.mini_game.reset_bite();
+            return false;
+        }
+
+        if self.args.shake_only {
+            // The player is reeling, only watch for the end of the bite
+            sleep(Duration::from_millis(250), &SHUTDOWN);
+            return true;
+        }
+
+        let hook = self.mini_game.find_hook(&screen);
+        let fish = if hook.fish_on {
+            self.mini_game.get_fish(&screen)
         } else {
-            info!("The bite is over");
-            enigo.button(Button::Left, Release).expect("Packup the rod");
-            stats.add_fishing_time(fishing_time.elapsed().as_secs());
-            break;
+            None
+        };
+        let fish_confidence = fish.as_ref().map_or(0, |fish| fish.confidence);
+
+        // Noisy frame, keep doing the same until we see clearly again
+        let (Some(fish), Some(hook_position)) = (
+            fish.filter(FishPosition::reliable),
+            hook.position.clone().filter(|_| hook.reliable()),
+        ) else {
+            info!(
+                "Holding, hook confidence is {}% and fish confidence is {fish_confidence}%",
+                hook.confidence
+            );
+            if let Some(movement) = self.last_move {
+                self.steer(movement);
+            }
+            sleep_with_jitter(self.args.sensitivity, 3, &SHUTDOWN);
+            return true;
         };
 
+        // Get current fish position
+        let fish_x = fish.point.x.cast_signed();
+
+        let mini_game = &self.mini_game;
         let fish_pos_as_minigame_bar_percentage = (((fish_x - mini_game.point1.x.cast_signed())
             .bad_cast()
             / mini_game.get_size().width.cast_signed().bad_cast())
@@ -456,167 +929,399 @@ fn fishing_loop(
         // Check if fish is very far left or very far right
         if fish_pos_as_minigame_bar_percentage < mini_game_percentage_edge_treshold {
             info!("Giving some slack...");
-            enigo
-                .button(Button::Left, Release)
-                .expect("Failed giving slack");
-            continue;
+            self.steer(Move::Left);
+            return true;
         } else if fish_pos_as_minigame_bar_percentage > 100 - mini_game_percentage_edge_treshold {
             info!("Tighting the line...");
-            enigo
-                .button(Button::Left, Press)
-                .expect("Failed keeping the line tight");
-            continue;
+            self.steer(Move::Right);
+            return true;
         }
 
-        let hook_x = hook
-            .position
-            .expect("Can't find the hook")
-            .absolute_mid_x
-            .cast_signed();
+        let hook_x = hook_position.absolute_mid_x.cast_signed();
 
         // INFO: As a side effect I did not explain yet, it tends to keep
         //       the fish on the 20% of the hook bar (pretty smart strategy IMO)
         let range = fish_x - hook_x;
-        let speed = hook_x - previous_hook_x;
-        match Move::decision(hook.length.cast_signed(), range, speed, 5) {
-            Move::Left => {
-                info!("<== To the left <==");
-                enigo.button(Button::Left, Release).expect("Going left");
+        let speed = hook_x - self.previous_hook_x;
+        let movement = Move::decision(hook.length.cast_signed(), range, speed, 5);
+        match movement {
+            Move::Left => info!("<== To the left <=="),
+            Move::Right => info!("==> To the right ==>"),
+            Move::Spam => info!("=== Spamming, the fish is close ==="),
+        }
+        self.steer(movement);
+
+        info!(
+            "Found fish at x={fish_x} ({}% confidence) - distance fish<->hook is {range} \
+            - hook speed is {speed} - hook confidence is {}%",
+            fish.confidence, hook.confidence
+        );
+
+        sleep_with_jitter(self.args.sensitivity, 3, &SHUTDOWN);
+        self.previous_hook_x = hook_x; // update previous hook position
+
+        true
+    }
+
+    fn resolve(&mut self) -> Option<Catch> {
+        info!("Fishing ended!");
+        self.previous_hook_x = 0;
+        self.last_move = None;
+
+        // Wait for the catch notification
+        sleep(Duration::from_millis(500), &SHUTDOWN);
+        self.recorder.wait_fresh_frame(&SHUTDOWN);
+        let screen = self
+            .recorder
+            .take_screenshot()
+            .expect("Couldn't take screenshot");
+        let catch = Catch::read(&screen);
+        match &catch {
+            Some(catch) => info!("Caught {}", catch.name),
+            None => info!("Couldn't identify the catch"),
+        }
+
+        // After fishing interaction, reel again
+        sleep_with_jitter(1500, 100, &SHUTDOWN);
+
+        catch
+    }
+
+    fn inventory_full(&mut self) -> bool {
+        // Text is easily misread on a single frame, the notification stays for a while
+        for _ in 0..INVENTORY_FULL_FRAMES {
+            self.recorder.wait_fresh_frame(&SHUTDOWN);
+            let screen = self
+                .recorder
+                .take_screenshot()
+                .expect("Couldn't take screenshot");
+            if INVENTORY_FULL.detect(&screen, None).is_none() {
+                return false;
+            }
+        }
+
+        warn!("Inventory is full");
+        match self.args.inventory_full {
+            InventoryPolicy::Stop => {
+                info!("Closing due to full inventory...");
+                SHUTDOWN.store(true, Ordering::Relaxed);
             }
-            Move::Right => {
-                info!("==> To the right ==>");
-                enigo.button(Button::Left, Press).expect("Going right");
+            InventoryPolicy::Pause => {
+                // Ring the terminal bell
+                eprint!("\x07");
+                warn!("Paused, press <F8> to resume");
+                RESUME.store(false, Ordering::Relaxed);
+                while !RESUME.load(Ordering::Relaxed) && !SHUTDOWN.load(Ordering::Relaxed) {
+                    sleep(Duration::from_millis(200), &SHUTDOWN);
+                }
+                info!("Resuming");
+                self.recover();
             }
-            Move::Spam => {
-                info!("=== Spamming, the fish is close ===");
-                enigo.button(Button::Left, Click).expect("Clicking failed");
+            InventoryPolicy::Sell => {
+                let command = self
+                    .args
+                    .sell_command
+                    .as_deref()
+                    .expect("Sell policy without any sell command");
+                info!("Selling with `{command}`...");
+                match inventory::sell(command) {
+                    Ok(()) => self.recover(),
+                    Err(e) => {
+                        warn!("{e}, closing...");
+                        SHUTDOWN.store(true, Ordering::Relaxed);
+                    }
+                }
             }
         }
 
-        info!("Found fish at x={fish_x} - distance fish<->hook is {range} - hook speed is {speed}");
+        true
+    }
 
-        sleep_with_jitter(args.sensitivity, 3, &SHUTDOWN);
-        previous_hook_x = hook_x; // update previous hook position
+    fn keep_alive(&mut self) {
+        match self.args.keep_alive {
+            KeepAlive::None => (),
+            KeepAlive::Nudge => {
+                info!("Nudging the camera to stay active");
+                for offset in [10, -10] {
+                    self.enigo
+                        .button(Button::Right, Press)
+                        .expect("Couldn't grab the camera");
+                    sleep(Duration::from_millis(50), &SHUTDOWN);
+                    self.enigo
+                        .move_mouse(offset, 0, Rel)
+                        .expect("Couldn't turn the camera");
+                    sleep(Duration::from_millis(50), &SHUTDOWN);
+                    self.enigo
+                        .button(Button::Right, Release)
+                        .expect("Couldn't release the camera");
+                }
+            }
+            KeepAlive::Jump => {
+                info!("Jumping to stay active");
+                JUMPING.store(true, Ordering::Relaxed);
+                simulate(&rdev::EventType::KeyPress(Key::Space)).expect("Couldn't press <SPACE>");
+                sleep_with_jitter(50, 10, &SHUTDOWN);
+                simulate(&rdev::EventType::KeyRelease(Key::Space))
+                    .expect("Couldn't release <SPACE>");
+                // Wait for the listener to see the key press, and for the landing
+                sleep(Duration::from_millis(500), &SHUTDOWN);
+                JUMPING.store(false, Ordering::Relaxed);
+            }
+        }
     }
-}
 
-/// Start the fishing process
-fn reels(
-    enigo: &mut Enigo,
-    last_shake_time: &mut Instant,
-    shake_count: &mut u8,
-    safe_point: &Point,
-    stats: &mut Stats,
-) {
-    // Move mouse
-    enigo
-        .move_mouse_ig_abs(safe_point.x.cast_signed(), safe_point.y.cast_signed())
-        .expect("Can't move mouse");
+    fn check_scene(&mut self) -> bool {
+        let screen = self
+            .recorder
+            .take_screenshot()
+            .expect("Couldn't take screenshot");
+        let change = match self.scene.check(&screen) {
+            Ok(Some(change)) => change,
+            Ok(None) => return false,
+            Err(e) => {
+                warn!(
+                    "Closing because the character keeps moving, is it still at the fishing spot? {e}"
+                );
+                SHUTDOWN.store(true, Ordering::Relaxed);
+                return true;
+            }
+        };
+        warn!("Scene changed: {change:?}");
 
-    // Click to be sure we are not shaking
-    enigo
-        .button(Button::Left, Click)
-        .expect("Can't click before reel");
-    sleep_with_jitter(70, 10, &SHUTDOWN);
+        // Wait for the respawn or the teleport
+        let start = Instant::now();
+        while self.scene.scene.check(
+            &self
+                .recorder
+                .take_screenshot()
+                .expect("Couldn't take screenshot"),
+        ) == Some(SceneChange::Loading)
+        {
+            if start.elapsed() > Duration::from_mins(1) {
+                warn!("Closing because the game is stuck loading");
+                SHUTDOWN.store(true, Ordering::Relaxed);
+                return true;
+            }
+            if SHUTDOWN.load(Ordering::Relaxed) {
+                return true;
+            }
+            sleep(Duration::from_secs(1), &SHUTDOWN);
+        }
 
-    info!("Reeling...");
-    // Casting motion
-    enigo
-        .button(Button::Left, Press)
-        .expect("Can't backswing: failed to press mouse button");
-    sleep_with_jitter(900, 300, &SHUTDOWN);
-    enigo
-        .button(Button::Left, Release)
-        .expect("Can't release the line: failed to release mouse button");
+        info!("Getting back in a fishing position");
+        self.restart();
 
-    if stats.enabled {
-        stats.reels.add_assign(1);
+        true
     }
 
-    *shake_count = 0;
-    *last_shake_time = Instant::now();
-}
+    fn check_drift(&mut self) -> bool {
+        let screen = self
+            .recorder
+            .take_screenshot()
+            .expect("Couldn't take screenshot");
+        let shift = self.scene.scene.shift(&screen);
+        let walks = match self.drift.correct(shift) {
+            Ok(walks) if walks.is_empty() => return false,
+            Ok(walks) => walks,
+            Err(e) => {
+                warn!("Closing because the character drifted too far from the fishing spot: {e}");
+                SHUTDOWN.store(true, Ordering::Relaxed);
+                return false;
+            }
+        };
 
-/// Returns the coordinates of the shake bubble
-fn check_shake(
-    #[allow(unused_variables)] enigo: &mut Enigo,
-    recorder: &mut ScreenRecorder,
-    region: &Region,
-    #[allow(unused_variables)] safe_point: &Point,
-    args: &Args,
-) -> Option<(Point, RgbImage)> {
-    let [x_min, y_min, x_max, y_max] = region.corners().map(u32::cast_signed);
+        info!("Drifted by {shift:?}, walking back");
+        for walk in walks {
+            let key = match walk {
+                Walk::Forward => Key::KeyW,
+                Walk::Backward => Key::KeyS,
+                Walk::Left => Key::KeyA,
+                Walk::Right => Key::KeyD,
+            };
+            simulate(&rdev::EventType::KeyPress(key)).expect("Couldn't start walking");
+            sleep(Duration::from_millis(150), &SHUTDOWN);
+            simulate(&rdev::EventType::KeyRelease(key)).expect("Couldn't stop walking");
+        }
 
-    // Move cursor out of the region (Sober creates a custom cursor)
-    #[cfg(target_os = "linux")]
-    {
-        // Sleep 1 / 3 to be sure we correctly move the cursor
-        sleep(Duration::from_millis((args.lag / 3).into()), &SHUTDOWN);
-        enigo
-            .move_mouse_ig_abs(safe_point.x.cast_signed(), safe_point.y.cast_signed())
-            .expect("Can't move mouse");
-        // Sleep 2 / 3 to be sure the screenshot won't capture our cursor
-        sleep(Duration::from_millis((args.lag * 2 / 3).into()), &SHUTDOWN);
+        true
     }
 
-    #[cfg(not(target_os = "linux"))]
-    {
-        sleep(Duration::from_millis(args.lag.into()), &SHUTDOWN);
+    fn recover(&mut self) {
+        let mut screen = self
+            .recorder
+            .take_screenshot()
+            .expect("Couldn't take screenshot");
+
+        // The hotbar may have been covered when starting
+        if self.hotbar.is_none() {
+            self.hotbar = Hotbar::search(&screen);
+        }
+        let Some(hotbar) = self.hotbar.as_ref() else {
+            warn!("Hotbar not found, can't tell if the rod is held");
+            return;
+        };
+
+        // Pressing the key of the held item unequips it, so the highlight has to be missing
+        // on several frames
+        for _ in 0..ROD_MISSING_FRAMES {
+            if hotbar.selected(&screen) == Some(self.rod_slot) {
+                info!("Rod is already held");
+                return;
+            }
+
+            self.recorder.wait_fresh_frame(&SHUTDOWN);
+            screen = self
+                .recorder
+                .take_screenshot()
+                .expect("Couldn't take screenshot");
+        }
+
+        info!("Rod isn't held, selecting it");
+        select_slot(self.rod_slot).expect("Couldn't select the rod");
     }
 
-    let pure_white = ColorTarget {
-        color: Rgb([0xff, 0xff, 0xff]),
-        variation: 1,
-    };
+    fn tidy_up(&mut self) -> Vec<&'static str> {
+        let screen = self
+            .recorder
+            .take_screenshot()
+            .expect("Couldn't take screenshot");
+
+        if self.server_offline(&screen) {
+            return vec![SERVER_OFFLINE.name];
+        }
+
+        let mut handled = dismiss_overlays(
+            self.enigo,
+            &screen,
+            self.roblox_button.as_ref(),
+            &[SCOREBOARD, CHAT, QUEST, IDLE_WARNING],
+            &SHUTDOWN,
+        );
+        handled.extend(self.popups.take());
+
+        handled
+    }
+}
 
-    // Check image from bottom to top helps up leveraging broadcast messages that are
-    // overlaping with the shaking area
+/// Select an item from the hotbar
+/// FIXME: This doesn't work properly on all platforms (Wayland)
+fn select_slot(slot: u16) -> Result<(), rdev::SimulateError> {
+    simulate(&rdev::EventType::KeyPress(
+        match slot {
+            1 => Ok(Key::Num1),
+            2 => Ok(Key::Num2),
+            3 => Ok(Key::Num3),
+            4 => Ok(Key::Num4),
+            5 => Ok(Key::Num5),
+            6 => Ok(Key::Num6),
+            7 => Ok(Key::Num7),
+            8 => Ok(Key::Num8),
+            9 => Ok(Key::Num9),
+            _ => Err(()),
+        }
+        .expect("Unkown requested key"),
+    ))
+}
+
+/// Press and release a key
+fn press_key(key: Key) {
+    simulate(&rdev::EventType::KeyPress(key)).expect("Couldn't press key");
+    sleep_with_jitter(50, 10, &SHUTDOWN);
+    simulate(&rdev::EventType::KeyRelease(key)).expect("Couldn't release key");
+}
+
+/// Returns the area of the mouse cursor.
+/// Sober creates a custom cursor that ends up in the screenshot
+fn cursor_mask(enigo: &Enigo, screen: &RgbImage) -> Option<Region> {
+    enigo.location().ok().map(|(x, y)| {
+        let (x, y) = (x.max(0).cast_unsigned(), y.max(0).cast_unsigned());
+        let size = screen.height() / 100;
+        Region {
+            point1: Point {
+                x: x.saturating_sub(size),
+                y: y.saturating_sub(size),
+            },
+            // The cursor goes down and right from its hotspot
+            point2: Point {
+                x: (x + size * 3).min(screen.width() - 1),
+                y: (y + size * 4).min(screen.height() - 1),
+            },
+        }
+    })
+}
+
+/// Returns the shake bubble, if any, and the screen it was searched on
+fn check_shake(
+    enigo: &mut Enigo,
+    recorder: &mut ScreenRecorder,
+    region: &Region,
+    popups: &mut OverlayWatch,
+    stale_frame: &mut u64,
+) -> (Option<Bubble>, RgbImage) {
+    // Be sure the screenshot has been captured after our last input
+    recorder.wait_frame_after(*stale_frame, &SHUTDOWN);
+    *stale_frame = recorder.sequence();
     let screen = recorder.take_screenshot().expect("Can't take screenshot");
 
-    let shake_point = (y_min..=y_max)
-        .rev()
-        .flat_map(|y| (x_min..=x_max).map(move |x| (x, y)))
-        .find_map(|(x, y)| {
-            let pixel = screen.get_pixel(x.cast_unsigned(), y.cast_unsigned());
+    let cursor = cursor_mask(enigo, &screen);
 
-            pure_white.matches(pixel).then(|| Point {
-                x: x.cast_unsigned() + 20,
-                y: y.cast_unsigned() - 10,
-            })
-        });
+    // Popups may cover the shake area, don't look for bubbles under them
+    let sightings = popups.check(&screen);
+    let masks = cursor
+        .into_iter()
+        .chain(popups.areas().iter().cloned())
+        .collect::<Vec<_>>();
+    let bubbles = search_bubbles(&screen, region, &masks);
+
+    for sighting in &sightings {
+        sighting.dismiss(enigo, &screen, &SHUTDOWN);
+    }
+    if !sightings.is_empty() {
+        *stale_frame = recorder.sequence();
+    }
 
     #[cfg(feature = "imageproc")]
     {
         use fischy::utils::debug::Drawable;
         use std::sync::Arc;
 
-        if let Some(p) = shake_point.clone() {
-            p.draw_async(Arc::new(screen.clone()), "shakes/point.png", false);
+        if !bubbles.is_empty() {
+            bubbles
+                .iter()
+                .map(|b| b.bounds.clone())
+                .collect::<Vec<_>>()
+                .draw_async(Arc::new(screen.clone()), "shakes/candidates.png", false);
         }
     }
 
-    shake_point.map(|p| (p, screen))
-}
+    let bubble = bubbles
+        .into_iter()
+        .next()
+        .filter(|b| b.score >= MIN_BUBBLE_SCORE);
 
-/// Initialize where the player is looking
-fn initialize_viewpoint(enigo: &mut Enigo, screen_dims: &Dimensions, cond: &AtomicBool) {
-    let padding = screen_dims.width * 20 / 100;
+    #[cfg(feature = "imageproc")]
+    {
+        use fischy::utils::debug::Drawable;
+        use std::sync::Arc;
 
-    // "Safepoint"
-    enigo
-        .move_mouse_ig_abs(screen_dims.width.cast_signed() / 2, padding.cast_signed())
-        .expect("Going to safepoint failed");
+        if let Some(b) = bubble.as_ref() {
+            b.center
+                .clone()
+                .draw_async(Arc::new(screen.clone()), "shakes/point.png", false);
+        }
+    }
 
-    // Looking at the floor
-    let movement = (0, screen_dims.height.cast_signed() / 3);
+    (bubble, screen)
+}
 
-    let steps = 2;
-    (0..=steps).for_each(|_| {
+/// Drag the camera down `steps` times, by `distance` pixels
+fn look_down(enigo: &mut Enigo, distance: i32, steps: u32, cond: &AtomicBool) {
+    (0..steps).for_each(|_| {
         enigo.button(Button::Right, Press).expect("Pressing failed");
         sleep(Duration::from_millis(100), cond);
 
         enigo
-            .move_mouse(movement.0, movement.1, Rel)
+            .move_mouse(0, distance, Rel)
             .expect("Going down failed");
         sleep(Duration::from_millis(100), cond);
 
@@ -628,30 +1333,98 @@ fn initialize_viewpoint(enigo: &mut Enigo, screen_dims: &Dimensions, cond: &Atom
 
         // Back to initial point
         enigo
-            .move_mouse(-movement.0, -movement.1, Rel)
+            .move_mouse(0, -distance, Rel)
             .expect("Resetting position failed");
         sleep(Duration::from_millis(100), cond);
     });
+}
+
+/// Look down at the water, then check the view and adjust the camera until it looks right
+fn initialize_viewpoint(
+    enigo: &mut Enigo,
+    recorder: &mut ScreenRecorder,
+    mini_game: &Region,
+    args: &Args,
+    cond: &AtomicBool,
+) -> Result<(), String> {
+    let (width, height) = (recorder.dimensions.width, recorder.dimensions.height);
+    let padding = width * 20 / 100;
+
+    // "Safepoint"
+    enigo
+        .move_mouse_ig_abs(width.cast_signed() / 2, padding.cast_signed())
+        .expect("Going to safepoint failed");
+
+    // Looking at the floor
+    look_down(enigo, height.cast_signed() / 3, 3, cond);
 
     // Zoom
     enigo
         .scroll_ig(-Enigo::max_scroll(), Vertical)
         .expect("Can't zoom in");
     enigo.scroll_ig(1, Vertical).expect("Can't zoom out");
+
+    let profile = ViewProfile {
+        water: args.water_color.unwrap_or(WATER),
+        ..ViewProfile::default()
+    };
+    let mut adjustments = 0;
+    loop {
+        // Let the camera settle
+        sleep(Duration::from_millis(300), cond);
+        recorder.wait_fresh_frame(cond);
+        let screen = recorder
+            .take_screenshot()
+            .expect("Couldn't take screenshot");
+
+        let view = View::measure(&screen, mini_game, &profile);
+        let report = format!(
+            "{}% water, {}% occluded, {}% noise under the minigame",
+            view.water, view.occlusion, view.mini_game_noise
+        );
+        info!("Camera view: {report}");
+
+        let Some(issue) = view.issue(&profile) else {
+            return Ok(());
+        };
+        if adjustments >= MAX_CAMERA_ADJUSTMENTS || cond.load(Ordering::Relaxed) {
+            return Err(format!(
+                "{issue:?} after {adjustments} adjustments ({report})"
+            ));
+        }
+        adjustments += 1;
+
+        info!("Adjusting the camera: {issue:?}");
+        match issue {
+            // Further away, the character and what stands around it get smaller
+            ViewIssue::Occluded | ViewIssue::MiniGameCovered => {
+                enigo.scroll_ig(1, Vertical).expect("Can't zoom out");
+            }
+            ViewIssue::NotEnoughWater => look_down(enigo, height.cast_signed() / 6, 1, cond),
+        }
+    }
 }
 
 /// Register specific keypress that will stop the program
 fn register_keybinds() {
     thread::spawn(|| {
-        listen(|e| {
-            if let Event {
+        listen(|e| match e {
+            Event {
+                event_type: KeyPress(Key::Space),
+                ..
+            } if JUMPING.load(Ordering::Relaxed) => (),
+            Event {
                 event_type: KeyPress(Key::Escape | Key::Space),
                 ..
-            } = e
-            {
+            } => {
                 info!("Closing due to key press...");
                 SHUTDOWN.store(true, Ordering::Relaxed);
             }
+            Event {
+                event_type: KeyPress(Key::F8),
+                ..
+            } => RESUME.store(true, Ordering::Relaxed),
+            _ => (),
         })
         .expect("Can't listen to keyboard");
     });
@@ -1,3 +1,37 @@
+use clap::ValueEnum;
+use image::Rgb;
+
+/// How shake bubbles are hit
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ShakeMode {
+    /// Click on the bubbles
+    Click,
+    /// Press `Return` on the bubbles selected by the UI navigation, enabling it if needed
+    Navigation,
+}
+
+/// Input sent from time to time, so Roblox doesn't think we are idle
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum KeepAlive {
+    /// Don't send anything
+    None,
+    /// Slightly turn the camera, then turn it back
+    Nudge,
+    /// Jump
+    Jump,
+}
+
+/// What to do when the inventory is full
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InventoryPolicy {
+    /// Stop the macro
+    Stop,
+    /// Alert and wait for `<F8>` to resume
+    Pause,
+    /// Run the sell command, then resume
+    Sell,
+}
+
 /// Parse rod control
 ///
 /// # Errors
@@ -15,3 +49,49 @@ pub fn rod_position_parser(s: &str) -> Result<u16, String> {
         Err(format!("Value must be between {min} and {max}, got {val}"))
     }
 }
+
+/// Parse a percentage
+///
+/// # Errors
+/// If user provided wrong value
+pub fn percentage_parser(s: &str) -> Result<u8, String> {
+    let val = s
+        .parse()
+        .map_err(|_| format!("`{s}` is not a valid number"))?;
+
+    let min = 1;
+    let max = 100;
+    if (min..=max).contains(&val) {
+        Ok(val)
+    } else {
+        Err(format!("Value must be between {min} and {max}, got {val}"))
+    }
+}
+
+/// Parse a color written as `RRGGBB`, the leading `#` is optional
+///
+/// # Errors
+/// If user provided wrong value
+pub fn color_parser(s: &str) -> Result<Rgb<u8>, String> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!("`{s}` is not a color like `2b6c9e`"));
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or_default();
+    Ok(Rgb([channel(0), channel(2), channel(4)]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_colors() {
+        assert_eq!(color_parser("2b6c9e"), Ok(Rgb([0x2b, 0x6c, 0x9e])));
+        assert_eq!(color_parser("#FF0001"), Ok(Rgb([0xff, 0x00, 0x01])));
+        for wrong in ["", "2b6c9", "2b6c9e0", "2b6c9g", "+b6c9e", "é2b6c9"] {
+            assert!(color_parser(wrong).is_err());
+        }
+    }
+}
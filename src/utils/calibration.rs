@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+
+use image::{Rgb, RgbImage};
+use log::{info, warn};
+
+use crate::utils::{
+    colors::{ColorTarget, Matcher},
+    geometry::Region,
+};
+
+/// Frames used to learn the palette
+const CALIBRATION_FRAMES: u32 = 5;
+
+/// Frames used to check the learned palette
+const VALIDATION_FRAMES: u32 = 5;
+
+/// Percentage of the validation frames where a learned color has to be seen
+const MIN_VALIDATION: u32 = 60;
+
+/// Bits dropped on each channel when grouping colors
+const QUANTIZATION: u8 = 3;
+
+/// Colors of the mini-game, learned during the first frames of a bite.
+/// A missing color means the default detection is used
+#[derive(Clone)]
+pub struct Palette {
+    /// Mini-game bar
+    pub background: ColorTarget,
+    /// Hook bar
+    pub hook: Option<ColorTarget>,
+    /// Fish cursor
+    pub fish: Option<ColorTarget>,
+}
+
+/// Accumulate colors of the mini-game until we can deduce a palette
+#[derive(Default)]
+pub struct Calibration {
+    frames: u32,
+    /// Quantized color -> (pixel count, sum of each channel)
+    histogram: HashMap<[u8; 3], (u64, [u64; 3])>,
+    /// Palette deduced from the histogram, checked against the next frames
+    candidate: Option<Palette>,
+    /// Validation frames where the learned hook and fish have been seen
+    seen: [u32; 2],
+}
+
+/// Returns the left-most and right-most pixels of the scanlines matching the color
+fn span(screen: &RgbImage, region: &Region, target: &ColorTarget) -> Option<(u32, u32)> {
+    let [x_min, _, x_max, _] = region.corners();
+    region
+        .scanlines(5)
+        .into_iter()
+        .flat_map(|y| {
+            (x_min..=x_max)
+                .filter(move |&x| target.matches(screen.get_pixel(x, y)))
+                .map(|x| (x, x))
+        })
+        .reduce(|(l1, r1), (l2, r2)| (l1.min(l2), r1.max(r2)))
+}
+
+impl Calibration {
+    /// Sample a frame of the mini-game
+    ///
+    /// # Return
+    /// The palette, once enough frames have been seen
+    pub fn observe(&mut self, screen: &RgbImage, region: &Region) -> Option<Palette> {
+        if self.candidate.is_some() {
+            return self.validate(screen, region);
+        }
+
+        let [x_min, _, x_max, _] = region.corners();
+        for y in region.scanlines(5) {
+            for x in x_min..=x_max {
+                let Rgb(pixel) = *screen.get_pixel(x, y);
+                let (count, sum) = self
+                    .histogram
+                    .entry(pixel.map(|c| c >> QUANTIZATION))
+                    .or_default();
+                *count += 1;
+                sum.iter_mut()
+                    .zip(pixel)
+                    .for_each(|(s, c)| *s += u64::from(c));
+            }
+        }
+
+        self.frames += 1;
+        if self.frames >= CALIBRATION_FRAMES {
+            self.candidate = self.palette();
+            self.frames = 0;
+        }
+
+        None
+    }
+
+    /// Check that the learned colors are found again, the frame or the arrows
+    /// could have been mistaken for the fish
+    ///
+    /// # Return
+    /// The palette, without the colors which weren't seen enough, once the check is over
+    fn validate(&mut self, screen: &RgbImage, region: &Region) -> Option<Palette> {
+        let candidate = self.candidate.as_ref()?;
+        let width = region.get_size().width;
+
+        // The hook is a large area
+        if let Some(hook) = &candidate.hook
+            && span(screen, region, hook).is_some_and(|(l, r)| r - l >= width * 5 / 100)
+        {
+            self.seen[0] += 1;
+        }
+
+        // The fish is a narrow cursor, while the frame and the arrows are on both sides
+        if let Some(fish) = &candidate.fish
+            && span(screen, region, fish).is_some_and(|(l, r)| r - l <= width * 10 / 100)
+        {
+            self.seen[1] += 1;
+        }
+
+        self.frames += 1;
+        if self.frames < VALIDATION_FRAMES {
+            return None;
+        }
+
+        let mut palette = self.candidate.take()?;
+        let confirmed = |seen: u32| seen * 100 >= MIN_VALIDATION * VALIDATION_FRAMES;
+        if palette.hook.is_some() && !confirmed(self.seen[0]) {
+            warn!("Learned hook color doesn't match the next frames, using the default one");
+            palette.hook = None;
+        }
+        if palette.fish.is_some() && !confirmed(self.seen[1]) {
+            warn!("Learned fish color doesn't match the next frames, using the default ones");
+            palette.fish = None;
+        }
+
+        Some(palette)
+    }
+
+    /// Deduce the palette from the accumulated colors
+    fn palette(&self) -> Option<Palette> {
+        let total = self.histogram.values().map(|(count, _)| count).sum::<u64>();
+
+        // Average color of each group, most frequent first
+        let mut colors = self
+            .histogram
+            .values()
+            .map(|(count, sum)| {
+                (
+                    *count,
+                    Rgb(sum.map(|s| u8::try_from(s / count).unwrap_or(u8::MAX))),
+                )
+            })
+            .collect::<Vec<_>>();
+        colors.sort_unstable_by_key(|(count, _)| u64::MAX - count);
+
+        let variation = (1 << QUANTIZATION) - 2;
+        let target = |color| ColorTarget {
+            color,
+            variation,
+            matcher: Matcher::Rgb,
+        };
+        let distinct = |a: &Rgb<u8>, b: &Rgb<u8>| {
+            a.0.iter()
+                .zip(b.0)
+                .any(|(x, y)| x.abs_diff(y) > 3 * variation)
+        };
+
+        // The bar takes most of the mini-game
+        let (_, background) = *colors.first()?;
+        let background_brightness = ColorTarget::brightness(&background);
+
+        // The hook is a large bright area
+        let hook = colors
+            .iter()
+            .find(|(_, c)| ColorTarget::brightness(c) > background_brightness + 60)
+            .map(|(_, c)| *c);
+
+        // The fish is small, but still takes a bit of room
+        let fish = colors
+            .iter()
+            .filter(|(count, _)| count * 1000 / total >= 2)
+            .find(|(_, c)| distinct(c, &background) && hook.as_ref().is_none_or(|h| distinct(c, h)))
+            .map(|(_, c)| *c);
+
+        info!("Learned mini-game palette: background={background:?}, hook={hook:?}, fish={fish:?}");
+
+        Some(Palette {
+            background: target(background),
+            hook: hook.map(target),
+            fish: fish.map(target),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::geometry::Point;
+
+    const BACKGROUND: Rgb<u8> = Rgb([0x30, 0x30, 0x38]);
+    const HOOK: Rgb<u8> = Rgb([0xe8, 0xe8, 0xe8]);
+    const FISH: Rgb<u8> = Rgb([0x43, 0x4b, 0x9b]);
+    const ARROWS: Rgb<u8> = Rgb([0x5f, 0x3b, 0x34]);
+
+    /// Mini-game bar with the hook on the left and the fish at `fish_x`
+    fn bar(arrows: bool, fish_x: u32) -> RgbImage {
+        RgbImage::from_fn(200, 40, |x, _| match x {
+            0..10 | 190.. if arrows => ARROWS,
+            40..100 => HOOK,
+            x if (fish_x..fish_x + 3).contains(&x) => FISH,
+            _ => BACKGROUND,
+        })
+    }
+
+    fn calibrate(frames: impl Iterator<Item = RgbImage>) -> Option<Palette> {
+        let region = Region {
+            point1: Point { x: 0, y: 0 },
+            point2: Point { x: 199, y: 39 },
+        };
+        let mut calibration = Calibration::default();
+
+        frames
+            .take((CALIBRATION_FRAMES + VALIDATION_FRAMES) as usize)
+            .map(|frame| calibration.observe(&frame, &region))
+            .last()
+            .flatten()
+    }
+
+    #[test]
+    fn learns_the_fish() {
+        let palette = calibrate((0..).map(|i| bar(false, 120 + i * 5))).expect("No palette");
+
+        assert!(palette.hook.is_some());
+        assert!(palette.fish.is_some_and(|fish| fish.matches(&FISH)));
+    }
+
+    #[test]
+    fn rejects_the_arrows() {
+        // The arrows are more frequent than the fish
+        let palette = calibrate((0..).map(|i| bar(true, 120 + i * 5))).expect("No palette");
+
+        assert!(palette.hook.is_some());
+        assert!(palette.fish.is_none());
+    }
+}
@@ -0,0 +1,230 @@
+use image::{Rgb, RgbImage};
+
+use crate::utils::{
+    colors::{ColorTarget, Matcher},
+    geometry::Region,
+};
+
+/// Cast power from which a cast is considered perfect
+pub const PERFECT_CAST: u8 = 95;
+
+/// Cast power meter, shown above the character while the button is held
+pub struct PowerBar {
+    /// Column where the fill is measured
+    x: u32,
+    /// Top of the meter
+    top: u32,
+    /// Bottom of the meter
+    bottom: u32,
+    /// Color of the unfilled part
+    empty: ColorTarget,
+}
+
+/// Vertical run of changed pixels of a single color
+#[derive(Clone, Copy)]
+struct Run {
+    top: u32,
+    bottom: u32,
+    color: Rgb<u8>,
+}
+
+/// Adjacent runs of similar color
+struct Cluster {
+    left: u32,
+    right: u32,
+    top: u32,
+    bottom: u32,
+    color: Rgb<u8>,
+}
+
+/// Check if two colors are close
+fn similar(a: Rgb<u8>, b: Rgb<u8>, variation: u8) -> bool {
+    ColorTarget {
+        color: a,
+        variation,
+        matcher: Matcher::Rgb,
+    }
+    .matches(&b)
+}
+
+/// Returns the longest run of the column which changed between the frames,
+/// the fill may have a slight gradient
+fn longest_run(before: &RgbImage, after: &RgbImage, x: u32, y_min: u32, y_max: u32) -> Option<Run> {
+    let changed = |y| !similar(*before.get_pixel(x, y), *after.get_pixel(x, y), 20);
+
+    let mut best: Option<Run> = None;
+    let mut current: Option<Run> = None;
+    for y in y_min..=y_max {
+        let pixel = after.get_pixel(x, y);
+        match current.as_mut() {
+            Some(run) if changed(y) && similar(*after.get_pixel(x, y - 1), *pixel, 12) => {
+                run.bottom = y;
+            }
+            _ => {
+                if let Some(run) = current.take()
+                    && best.is_none_or(|b| run.bottom - run.top > b.bottom - b.top)
+                {
+                    best = Some(run);
+                }
+                current = changed(y).then_some(Run {
+                    top: y,
+                    bottom: y,
+                    color: *pixel,
+                });
+            }
+        }
+    }
+
+    current
+        .into_iter()
+        .chain(best)
+        .max_by_key(|run| run.bottom - run.top)
+}
+
+impl PowerBar {
+    /// Locate the meter from two frames taken while it was filling up:
+    /// the fill is a thin and tall block of a single color which changed in between
+    #[must_use]
+    pub fn search(before: &RgbImage, after: &RgbImage, region: &Region) -> Option<Self> {
+        let [x_min, y_min, x_max, y_max] = region.corners();
+        let min_length = (y_max - y_min) / 10;
+
+        // Group the runs of neighbouring columns
+        let mut clusters: Vec<Cluster> = Vec::new();
+        for x in x_min..=x_max {
+            let Some(run) = longest_run(before, after, x, y_min, y_max)
+                .filter(|run| run.bottom - run.top >= min_length)
+            else {
+                continue;
+            };
+            match clusters.last_mut() {
+                Some(cluster)
+                    if cluster.right + 1 == x
+                        && run.top <= cluster.bottom
+                        && cluster.top <= run.bottom
+                        && similar(cluster.color, run.color, 30) =>
+                {
+                    cluster.right = x;
+                    cluster.top = cluster.top.min(run.top);
+                    cluster.bottom = cluster.bottom.max(run.bottom);
+                }
+                _ => clusters.push(Cluster {
+                    left: x,
+                    right: x,
+                    top: run.top,
+                    bottom: run.bottom,
+                    color: run.color,
+                }),
+            }
+        }
+
+        // The fill is thin and tall
+        let Cluster {
+            left,
+            right,
+            top: fill_top,
+            bottom,
+            ..
+        } = clusters
+            .into_iter()
+            .filter(|c| c.bottom - c.top >= (c.right - c.left + 1) * 2)
+            .max_by_key(|c| c.bottom - c.top)?;
+
+        // Only the new part of the fill changed, the rest of it is below
+        let x = left.midpoint(right);
+        let bottom = (bottom..y_max)
+            .take_while(|&y| similar(*after.get_pixel(x, y), *after.get_pixel(x, y + 1), 12))
+            .last()
+            .map_or(bottom, |y| y + 1);
+
+        // The empty part of the meter is above the fill
+        let empty = ColorTarget {
+            color: *before.get_pixel(x, fill_top),
+            variation: 10,
+            matcher: Matcher::Rgb,
+        };
+        let top = (y_min..fill_top)
+            .rev()
+            .take_while(|&y| empty.matches(after.get_pixel(x, y)))
+            .last()
+            .unwrap_or(fill_top);
+
+        let bar = Self {
+            x,
+            top,
+            bottom,
+            empty,
+        };
+
+        #[cfg(feature = "imageproc")]
+        {
+            use crate::{Point, utils::debug::Drawable};
+            use std::sync::Arc;
+
+            Region {
+                point1: Point {
+                    x: left,
+                    y: bar.top,
+                },
+                point2: Point {
+                    x: right,
+                    y: bar.bottom,
+                },
+            }
+            .draw_async(Arc::new(after.clone()), "power_bar.png", true);
+        }
+
+        Some(bar)
+    }
+
+    /// Returns the filled percentage of the meter
+    #[must_use]
+    pub fn fill(&self, screen: &RgbImage) -> u8 {
+        let filled = (self.top..=self.bottom)
+            .rev()
+            .take_while(|&y| !self.empty.matches(screen.get_pixel(self.x, y)))
+            .count();
+        let height = (self.bottom - self.top + 1) as usize;
+
+        u8::try_from(filled * 100 / height).unwrap_or(100)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::geometry::Point;
+
+    const BACKGROUND: Rgb<u8> = Rgb([0x6a, 0x8c, 0x5a]);
+    const EMPTY: Rgb<u8> = Rgb([0x30, 0x30, 0x30]);
+    const FILL: Rgb<u8> = Rgb([0x4c, 0xd9, 0x64]);
+
+    /// Meter filled to `fill` percent, with a character animating on its left
+    fn frame(fill: u32, pose: u32) -> RgbImage {
+        let byte = |value: u32| value.to_le_bytes()[0];
+        RgbImage::from_fn(200, 200, |x, y| match (x, y) {
+            (100..108, 40..140) if y >= 140 - fill => FILL,
+            (100..108, 40..140) => EMPTY,
+            // A textured character moves too
+            (10..90, 60..180) => Rgb([
+                byte(x * 37 + y * 91 + pose),
+                byte(x * 53 + y * 17 + pose * 3),
+                byte(y * 29 + pose),
+            ]),
+            _ => BACKGROUND,
+        })
+    }
+
+    #[test]
+    fn finds_the_meter_among_other_changes() {
+        let region = Region {
+            point1: Point { x: 0, y: 0 },
+            point2: Point { x: 199, y: 199 },
+        };
+        let bar = PowerBar::search(&frame(10, 0), &frame(40, 90), &region).expect("No bar");
+
+        assert!((100..108).contains(&bar.x));
+        assert_eq!(bar.fill(&frame(40, 90)), 40);
+        assert_eq!(bar.fill(&frame(75, 10)), 75);
+    }
+}
@@ -0,0 +1,281 @@
+use image::{Rgb, RgbImage};
+
+use crate::utils::{
+    colors::ColorTarget,
+    geometry::{Point, Region},
+    ocr::{fold, read_text},
+};
+
+/// Minimum OCR confidence to trust the banner text
+const MIN_CONFIDENCE: u8 = 60;
+
+/// Minimum difference between the channels of a saturated color
+const MIN_SATURATION: u8 = 40;
+
+/// Percentage of saturated pixels from which the banner isn't grey,
+/// below it they are only the anti-aliasing of the text
+const MIN_SATURATED: usize = 10;
+
+/// Mutations written before the fish name
+const MUTATIONS: [&str; 20] = [
+    "Abyssal",
+    "Albino",
+    "Atlantean",
+    "Darkened",
+    "Electric",
+    "Fossilized",
+    "Frozen",
+    "Ghastly",
+    "Glossy",
+    "Hexed",
+    "Lunar",
+    "Midas",
+    "Mosaic",
+    "Mythical",
+    "Negative",
+    "Shiny",
+    "Silver",
+    "Sinister",
+    "Sparkling",
+    "Translucent",
+];
+
+/// Rarity tier of a fish, given by the banner color
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rarity {
+    Trash,
+    Common,
+    Uncommon,
+    Unusual,
+    Rare,
+    Legendary,
+    Mythical,
+    Exotic,
+    Secret,
+}
+
+impl Rarity {
+    /// Banner color of each tier
+    const COLORS: [(Self, Rgb<u8>); 9] = [
+        (Self::Trash, Rgb([0x91, 0x91, 0x91])),
+        (Self::Common, Rgb([0xe6, 0xe6, 0xe6])),
+        (Self::Uncommon, Rgb([0x5c, 0xd6, 0x5c])),
+        (Self::Unusual, Rgb([0x9b, 0x74, 0xe8])),
+        (Self::Rare, Rgb([0x3f, 0x8c, 0xff])),
+        (Self::Legendary, Rgb([0xff, 0xb1, 0x2b])),
+        (Self::Mythical, Rgb([0xff, 0x3f, 0x6c])),
+        (Self::Exotic, Rgb([0xff, 0x6f, 0xf0])),
+        (Self::Secret, Rgb([0x22, 0xe3, 0xd6])),
+    ];
+
+    /// Returns the tier with the closest banner color
+    fn from_color(color: Rgb<u8>) -> Option<Self> {
+        Self::COLORS
+            .iter()
+            .map(|(rarity, Rgb(target))| {
+                let distance = target
+                    .iter()
+                    .zip(color.0)
+                    .map(|(t, c)| u32::from(t.abs_diff(c)))
+                    .sum::<u32>();
+                (*rarity, distance)
+            })
+            .filter(|(_, distance)| *distance < 120)
+            .min_by_key(|(_, distance)| *distance)
+            .map(|(rarity, _)| rarity)
+    }
+}
+
+/// Read the letters which look like digits as such
+fn digits(word: &str) -> String {
+    word.chars()
+        .map(|c| match c {
+            'i' | 'l' | 'I' | '!' | '|' => '1',
+            'o' | 'O' => '0',
+            's' | 'S' | '$' => '5',
+            c => c,
+        })
+        .collect()
+}
+
+/// Fish shown by the catch notification
+pub struct Catch {
+    pub name: String,
+    /// Weight, in kilograms
+    pub weight: Option<f32>,
+    pub rarity: Option<Rarity>,
+    pub mutation: Option<String>,
+}
+
+impl Catch {
+    /// Read the catch banner, above the hotbar
+    #[must_use]
+    pub fn read(screen: &RgbImage) -> Option<Self> {
+        let (width, height) = screen.dimensions();
+        let banner = Region {
+            point1: Point {
+                x: width * 30 / 100,
+                y: height * 72 / 100,
+            },
+            point2: Point {
+                x: width * 70 / 100,
+                y: height * 80 / 100,
+            },
+        };
+
+        let text = read_text(screen, &banner)?;
+        if text.confidence < MIN_CONFIDENCE {
+            return None;
+        }
+
+        let catch = Self::parse(&text.text, Self::banner_color(screen, &banner));
+        if catch.name.is_empty() {
+            return None;
+        }
+
+        Some(catch)
+    }
+
+    /// Split a banner like `Shiny Tuna (12.5kg)`
+    fn parse(text: &str, rarity: Option<Rarity>) -> Self {
+        let mut words = text
+            .split(|c: char| c.is_whitespace() || c == '(' || c == ')')
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>();
+
+        let weight = words
+            .iter()
+            .position(|word| word.ends_with("kg"))
+            .and_then(|i| digits(words.remove(i).trim_end_matches("kg")).parse().ok());
+
+        let mutation = words
+            .first()
+            .and_then(|word| MUTATIONS.iter().find(|m| fold(m) == fold(word)))
+            .map(ToString::to_string);
+        if mutation.is_some() {
+            words.remove(0);
+        }
+
+        Self {
+            name: words.join(" "),
+            weight,
+            rarity,
+            mutation,
+        }
+    }
+
+    /// Most common saturated color of the banner, or its most common grey
+    /// for the tiers without any color
+    fn banner_color(screen: &RgbImage, banner: &Region) -> Option<Rarity> {
+        let [x_min, y_min, x_max, y_max] = banner.corners();
+        let (mut saturated, mut greys): (Vec<(Rgb<u8>, usize)>, Vec<_>) = (Vec::new(), Vec::new());
+        for (x, y) in (x_min..=x_max)
+            .step_by(4)
+            .flat_map(|x| (y_min..=y_max).step_by(4).map(move |y| (x, y)))
+        {
+            let pixel = *screen.get_pixel(x, y);
+            // Skip the text outline, the dark background and the white text
+            if ColorTarget::brightness(&pixel) < 60 || pixel.0.iter().all(|&c| c >= 0xf0) {
+                continue;
+            }
+
+            let (min, max) = (pixel.0.iter().min(), pixel.0.iter().max());
+            let colors = match min.zip(max) {
+                Some((min, max)) if max - min >= MIN_SATURATION => &mut saturated,
+                _ => &mut greys,
+            };
+
+            // Group close colors together
+            let quantized = Rgb(pixel.0.map(|c| c & 0xf0));
+            match colors.iter_mut().find(|(color, _)| *color == quantized) {
+                Some((_, count)) => *count += 1,
+                None => colors.push((quantized, 1)),
+            }
+        }
+
+        let count = |colors: &[(Rgb<u8>, usize)]| colors.iter().map(|(_, c)| c).sum::<usize>();
+        let total = count(&saturated) + count(&greys);
+        let colors = if count(&saturated) * 100 >= total * MIN_SATURATED {
+            saturated
+        } else {
+            greys
+        };
+
+        colors
+            .into_iter()
+            .max_by_key(|(_, count)| *count)
+            .and_then(|(color, _)| Rarity::from_color(Rgb(color.0.map(|c| c | 0x08))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BANNER: Region = Region {
+        point1: Point { x: 0, y: 0 },
+        point2: Point { x: 199, y: 39 },
+    };
+
+    /// Banner of the color, mostly covered by white text outlined in grey
+    fn banner(color: Rgb<u8>) -> RgbImage {
+        RgbImage::from_fn(200, 40, |x, y| match (x % 12, y) {
+            (0..8, 6..34) => Rgb([0xff, 0xff, 0xff]),
+            (8, 6..34) => Rgb([0x90, 0x90, 0x94]),
+            (9, 6..34) => Rgb([0x20, 0x20, 0x20]),
+            _ if (4..36).contains(&y) => color,
+            _ => Rgb([0x10, 0x10, 0x14]),
+        })
+    }
+
+    #[test]
+    fn ignores_the_text() {
+        let rare = banner(Rarity::COLORS[4].1);
+        assert_eq!(Catch::banner_color(&rare, &BANNER), Some(Rarity::Rare));
+
+        let trash = banner(Rarity::COLORS[0].1);
+        assert_eq!(Catch::banner_color(&trash, &BANNER), Some(Rarity::Trash));
+    }
+
+    #[test]
+    fn parses_the_banner() {
+        let catch = Catch::parse("Shiny Tuna (12.5kg)", Some(Rarity::Rare));
+        assert_eq!(catch.name, "Tuna");
+        assert_eq!(catch.weight, Some(12.5));
+        assert_eq!(catch.mutation.as_deref(), Some("Shiny"));
+        assert_eq!(catch.rarity, Some(Rarity::Rare));
+
+        let catch = Catch::parse("Giant Squid (105kg)", None);
+        assert_eq!(catch.name, "Giant Squid");
+        assert_eq!(catch.weight, Some(105.));
+        assert_eq!(catch.mutation, None);
+    }
+
+    #[test]
+    fn parses_misread_letters() {
+        let catch = Catch::parse("Shlny Tuna (l2.5kg)", None);
+        assert_eq!(catch.name, "Tuna");
+        assert_eq!(catch.weight, Some(12.5));
+        assert_eq!(catch.mutation.as_deref(), Some("Shiny"));
+    }
+
+    #[test]
+    fn parses_a_wrong_weight() {
+        let catch = Catch::parse("Tuna (12.x5kg)", None);
+        assert_eq!(catch.name, "Tuna");
+        assert_eq!(catch.weight, None);
+
+        let catch = Catch::parse("Sinister Tuna", None);
+        assert_eq!(catch.name, "Tuna");
+        assert_eq!(catch.weight, None);
+        assert_eq!(catch.mutation.as_deref(), Some("Sinister"));
+
+        // Nothing left for the name, the banner is rejected
+        assert!(Catch::parse("Shiny (3kg)", None).name.is_empty());
+    }
+
+    #[test]
+    fn mutations_are_sorted() {
+        assert!(MUTATIONS.is_sorted());
+    }
+}
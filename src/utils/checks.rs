@@ -1,302 +1,143 @@
-use std::{
-    sync::atomic::{AtomicBool, Ordering},
-    time::Duration,
-};
+use enigo::Key;
+use image::Rgb;
 
-use enigo::{Button, Direction::Click, Enigo, Key, Keyboard, Mouse};
-use image::{Rgb, RgbImage};
-use log::info;
+use crate::utils::{
+    colors::{ColorTarget, Matcher},
+    overlays::{Action, Anchor, Overlay, Rule},
+};
 
-use crate::{
-    Scroller, sleep,
-    utils::{
-        colors::ColorTarget,
-        geometry::{Point, Region},
+/// Scoreboard, closed with `<TAB>`
+pub const SCOREBOARD: Overlay = Overlay {
+    name: "scoreboard",
+    anchor: Anchor::Screen,
+    area: [860, 50, 980, 500],
+    extent: None,
+    rule: Rule::Text {
+        // C$, Level, People
+        columns: &[980, 940, 860],
+        // TODO: Conservative treshold, could we go lower?
+        transitions: 10,
     },
+    action: Action::Key(Key::Tab),
 };
 
-fn text_detection(xs: &[u32], y_min: u32, y_max: u32, img: &RgbImage) -> usize {
-    xs.iter()
-        .map(|&x| {
-            (y_min..y_max)
-                .map(|y| ColorTarget::brightness(img.get_pixel(x, y)))
-                .collect::<Vec<_>>()
-                .windows(2)
-                .filter(|w| w[0].abs_diff(w[1]) > 100)
-                .count()
-        })
-        .sum::<usize>()
-}
-
-/// Close scoreboard if open
-///
-/// # Panics
-/// If couldn't close scoreboard
-pub fn scoreboard_check(enigo: &mut Enigo, img: &RgbImage) {
-    let x_positions = [
-        img.width() * 98 / 100, // C$
-        img.width() * 94 / 100, // Level
-        img.width() * 86 / 100, // People
-    ];
-    let [y_min, y_max] = [img.height() * 5 / 100, img.height() / 2];
-
-    #[cfg(feature = "imageproc")]
-    {
-        use crate::utils::debug::Drawable;
-        use std::sync::Arc;
-
-        x_positions
-            .map(|x| Region {
-                point1: Point { x: x - 1, y: y_min },
-                point2: Point { x: x + 1, y: y_max },
-            })
-            .to_vec()
-            .draw_async(Arc::new(img.clone()), "roblox_scoreboard.png", true);
-    }
-
-    // TODO: Conservative treshold, could we go lower?
-    if text_detection(&x_positions, y_min, y_max, img) >= 10 {
-        enigo.key(Key::Tab, Click).expect("Couldn't press <TAB>");
-    }
-}
-
-/// Close chat if open
-///
-/// # Panics
-/// If couldn't close the chat
-pub fn chat_check(enigo: &mut Enigo, img: &RgbImage, roblox_anchor: &Point, cond: &AtomicBool) {
-    // Where to click
-    let button = Point {
-        x: roblox_anchor.x + img.width() * 5 / 100,
-        y: roblox_anchor.y - 5,
-    };
-
-    // Where to check
-    let area = Region {
-        point1: Point {
-            x: button.x - img.width() * 8 / 1000,
-            y: button.y - img.height() * 5 / 1000,
-        },
-        point2: Point {
-            x: button.x + img.width() / 100,
-            y: button.y + img.height() * 2 / 100,
+/// Chat, closed with the button next to the Roblox one
+pub const CHAT: Overlay = Overlay {
+    name: "chat",
+    anchor: Anchor::RobloxButton,
+    area: [42, -10, 60, 15],
+    extent: None,
+    rule: Rule::Color {
+        target: ColorTarget {
+            color: Rgb([0xf7, 0xf7, 0xf8]),
+            variation: 2,
+            matcher: Matcher::Rgb,
         },
-    };
-
-    #[cfg(feature = "imageproc")]
-    {
-        use crate::utils::debug::Drawable;
-        use std::sync::Arc;
-
-        button
-            .clone()
-            .draw_async(Arc::new(img.clone()), "roblox_chat_button.png", true);
-        area.clone()
-            .draw_async(Arc::new(img.clone()), "roblox_chat_button_area.png", true);
-    }
-
-    let white_color_open_chat_button = ColorTarget {
-        color: Rgb([0xf7, 0xf7, 0xf8]),
-        variation: 2,
-    };
-    let [x_min, y_min, x_max, y_max] = area.corners();
-    let matching_pixels = (x_min..x_max)
-        .flat_map(|x| (y_min..y_max).map(move |y| (x, y)))
-        .filter(|(x, y)| white_color_open_chat_button.matches(img.get_pixel(*x, *y)))
-        .count();
-
-    let check = matching_pixels
-        / usize::try_from(((x_max - x_min) * (y_max - y_min)) / 100).unwrap_or(usize::MAX)
-        > 20; // treshold percentage of the same color in the area
-
-    if check {
-        enigo
-            .move_mouse_ig_abs(button.x.cast_signed(), button.y.cast_signed())
-            .expect("Couldn't move mouse to chat button");
-        sleep(Duration::from_millis(100), cond); // we may move the mouse too fast
-        enigo
-            .button(Button::Left, Click)
-            .expect("Couldn't close the chat");
-    }
-}
-
-/// Close quest panel if open
-///
-/// # Panics
-/// If couldn't close the quest panel
-pub fn quest_check(enigo: &mut Enigo, img: &RgbImage, roblox_anchor: &Point, cond: &AtomicBool) {
-    let final_y = roblox_anchor.y + img.height() * 45 / 1000; // 4.5%
-    let possible_y = roblox_anchor.y + img.height() * 338 / 1000; // 33.8%
-    let start_x = roblox_anchor.x + img.width() * 5 / 100;
-    let end_x = img.width() * 35 / 100;
-
-    #[cfg(feature = "imageproc")]
-    {
-        use crate::utils::debug::Drawable;
-        use std::sync::Arc;
-
-        vec![
-            Region {
-                point1: Point {
-                    x: roblox_anchor.x,
-                    y: final_y - 1,
-                },
-                point2: Point {
-                    x: end_x,
-                    y: final_y + 1,
-                },
-            },
-            Region {
-                point1: Point {
-                    x: roblox_anchor.x,
-                    y: possible_y - 1,
-                },
-                point2: Point {
-                    x: end_x,
-                    y: possible_y + 1,
-                },
-            },
-        ]
-        .draw_async(Arc::new(img.clone()), "quest_arrow_search.png", true);
-    }
-
-    let white = ColorTarget {
-        color: Rgb([0xff, 0xff, 0xff]),
-        variation: 0,
-    };
-
-    let segment_width = img.width() / 1000; // 0.1%
-    if let Some(found_x) = [final_y, possible_y].iter().find_map(|&y| {
-        (start_x..end_x).rev().find(|&x| {
-            (x..x + segment_width)
-                .filter(|&px| white.matches(img.get_pixel(px, y)))
-                .count()
-                >= segment_width as usize
-        })
-    }) {
-        #[cfg(feature = "imageproc")]
-        {
-            use crate::utils::debug::Drawable;
-            use std::sync::Arc;
-
-            Point {
-                x: found_x,
-                y: final_y,
-            }
-            .draw_async(Arc::new(img.clone()), "quest_arrow.png", true);
-        }
-
-        enigo
-            .move_mouse_ig_abs(found_x.cast_signed(), final_y.cast_signed())
-            .expect("Couldn't move mouse to quest arrow");
-        sleep(Duration::from_millis(100), cond);
-        enigo
-            .button(Button::Left, Click)
-            .expect("Couldn't close the quest panel");
-    }
-}
-
-/// Close if server have shutdown
-pub fn server_alive_check(img: &RgbImage, cond: &AtomicBool) -> bool {
-    let (win_w, win_h) = img.dimensions();
-    let (popup_w, popup_h) = (win_w * 10 / 100, win_h * 10 / 100);
+        percentage: 20,
+    },
+    action: Action::Click([50, -5]),
+};
 
-    let disconnected_popup = Region {
-        point1: Point {
-            x: win_w / 2 - popup_w,
-            y: win_h / 2 - popup_h,
-        },
-        point2: Point {
-            x: win_w / 2 + popup_w,
-            y: win_h / 2 + popup_h,
+/// Quest panel, closed with its arrow.
+/// Has to be checked after the chat, because the chat window moves the arrow
+pub const QUEST: Overlay = Overlay {
+    name: "quest panel",
+    anchor: Anchor::RobloxButton,
+    // Final and possible positions of the arrow
+    area: [50, 45, 330, 338],
+    extent: None,
+    rule: Rule::Mark {
+        target: ColorTarget {
+            color: Rgb([0xff, 0xff, 0xff]),
+            variation: 0,
+            matcher: Matcher::Rgb,
         },
-    };
-
-    #[cfg(feature = "imageproc")]
-    {
-        use crate::utils::debug::Drawable;
-        use std::sync::Arc;
-
-        disconnected_popup.clone().draw_async(
-            Arc::new(img.clone()),
-            "disconnected_popup.png",
-            true,
-        );
-    }
-
-    let gray = ColorTarget {
-        color: Rgb([0x39, 0x3b, 0x3d]),
-        variation: 0,
-    };
-
-    let [x_min, y_min, x_max, y_max] = disconnected_popup.corners();
-    let matching_pixels = (x_min..x_max)
-        .flat_map(|x| (y_min..y_max).map(move |y| (x, y)))
-        .filter(|(x, y)| gray.matches(img.get_pixel(*x, *y)))
-        .count();
-
-    let treshold = 70; // percentage of the same color in the area
-    let check = matching_pixels
-        / usize::try_from(((x_max - x_min) * (y_max - y_min)) / 100).unwrap_or(usize::MAX)
-        > treshold;
-    if check {
-        info!("Closing due to server offline...");
-        cond.store(true, Ordering::Relaxed);
-    }
-
-    !check
-}
+        length: 1,
+    },
+    action: Action::ClickMatch,
+};
 
-/// Dismiss max treasure maps warning
-///
-/// # Panics
-/// If couldn't close the treasure maps warning
-pub fn treasure_maps_check(enigo: &mut Enigo, img: &RgbImage, cond: &AtomicBool) {
-    let x_positions = [
-        img.width() * 94 / 100,
-        img.width() * 91 / 100,
-        img.width() * 89 / 100,
-    ];
+/// Disconnection popup, shown when the server have shutdown
+pub const SERVER_OFFLINE: Overlay = Overlay {
+    name: "server offline",
+    anchor: Anchor::Screen,
+    area: [400, 400, 600, 600],
+    extent: None,
+    rule: Rule::Color {
+        target: ColorTarget {
+            color: Rgb([0x39, 0x3b, 0x3d]),
+            variation: 4,
+            matcher: Matcher::Lab,
+        },
+        percentage: 70,
+    },
+    action: Action::Shutdown,
+};
 
-    let half = img.height() / 2 + img.height() / 100;
-    let shift = img.height() * 5 / 100;
-    let [y_min, y_max] = [half - shift, half + shift];
+/// Roblox warning shown before kicking idle players, closed with its button
+pub const IDLE_WARNING: Overlay = Overlay {
+    name: "idle warning",
+    anchor: Anchor::Screen,
+    area: [300, 380, 700, 480],
+    extent: None,
+    rule: Rule::Words(&["idle", "inactiv"]),
+    action: Action::Click([500, 560]),
+};
 
-    #[cfg(feature = "imageproc")]
-    {
-        use crate::utils::debug::Drawable;
-        use std::sync::Arc;
+/// Max treasure maps warning
+pub const TREASURE_MAPS: Overlay = Overlay {
+    name: "treasure maps warning",
+    anchor: Anchor::Screen,
+    area: [890, 460, 940, 560],
+    extent: None,
+    rule: Rule::Text {
+        columns: &[940, 910, 890],
+        transitions: 10,
+    },
+    action: Action::Click([920, 580]),
+};
 
-        x_positions
-            .map(|x| Region {
-                point1: Point { x: x - 1, y: y_min },
-                point2: Point { x: x + 1, y: y_max },
-            })
-            .to_vec()
-            .draw_async(Arc::new(img.clone()), "treasure_maps.png", true);
-    }
+/// Inventory full notification, the reaction depends on the user policy
+pub const INVENTORY_FULL: Overlay = Overlay {
+    name: "inventory full",
+    anchor: Anchor::Screen,
+    area: [300, 150, 700, 220],
+    extent: None,
+    rule: Rule::Words(&["full"]),
+    action: Action::Report,
+};
 
-    if text_detection(&x_positions, y_min, y_max, img) >= 10 {
-        let p = Point {
-            x: img.width() * 92 / 100,
-            y: img.height() * 58 / 100,
-        };
+/// Level-up banner, fading by itself
+pub const LEVEL_UP: Overlay = Overlay {
+    name: "level up",
+    anchor: Anchor::Screen,
+    area: [300, 100, 700, 180],
+    extent: Some([250, 80, 750, 200]),
+    rule: Rule::Words(&["level"]),
+    action: Action::Report,
+};
 
-        #[cfg(feature = "imageproc")]
-        {
-            use crate::utils::debug::Drawable;
-            use std::sync::Arc;
+/// Daily reward prompt, closed by clicking its claim button once read
+pub const DAILY_REWARD: Overlay = Overlay {
+    name: "daily reward",
+    anchor: Anchor::Screen,
+    // Inside the claim button, its edges would be read as text
+    area: [450, 675, 550, 725],
+    extent: Some([250, 230, 750, 760]),
+    rule: Rule::Words(&["claim"]),
+    action: Action::ClickMatch,
+};
 
-            p.clone()
-                .draw_async(Arc::new(img.clone()), "treasure_maps_button.png", true);
-        }
+/// Event announcement, closed with its cross
+pub const EVENT_ANNOUNCEMENT: Overlay = Overlay {
+    name: "event announcement",
+    anchor: Anchor::Screen,
+    area: [250, 180, 750, 250],
+    // The title is at the top of the panel
+    extent: Some([240, 170, 770, 650]),
+    rule: Rule::Words(&["event"]),
+    action: Action::Click([760, 190]),
+};
 
-        enigo
-            .move_mouse_ig_abs(p.x.cast_signed(), p.y.cast_signed())
-            .expect("Couldn't move mouse to dismiss button");
-        sleep(Duration::from_millis(100), cond);
-        enigo
-            .button(Button::Left, Click)
-            .expect("Couldn't dismiss the treasure maps warning");
-    }
-}
+/// Recurring popups that may cover the shake area
+pub const POPUPS: &[Overlay] = &[LEVEL_UP, DAILY_REWARD, EVENT_ANNOUNCEMENT];
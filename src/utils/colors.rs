@@ -1,19 +1,143 @@
 use image::Rgb;
 
+/// How a pixel is compared to the target color, the meaning of `variation` depends on it
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum Matcher {
+    /// Difference of each channel, up to `variation`
+    #[default]
+    Rgb,
+    /// Hue within `variation` degrees and saturation within `variation` percent,
+    /// the brightness is ignored
+    Hsv,
+    /// CIE Lab distance (ΔE 1976) up to `variation`
+    Lab,
+}
+
+#[derive(Clone)]
 pub struct ColorTarget {
     pub color: Rgb<u8>,
     pub variation: u8,
+    pub matcher: Matcher,
 }
 
-impl ColorTarget {
+/// Hue in degrees, saturation and value in percent
+fn hsv(pixel: Rgb<u8>) -> (i32, i32, i32) {
+    let [r, g, b] = pixel.0.map(i32::from);
+    let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+    let delta = max - min;
+
+    let hue = if delta == 0 {
+        0
+    } else if max == r {
+        (60 * (g - b) / delta).rem_euclid(360)
+    } else if max == g {
+        60 * (b - r) / delta + 120
+    } else {
+        60 * (r - g) / delta + 240
+    };
+    let saturation = if max == 0 { 0 } else { delta * 100 / max };
+
+    (hue, saturation, max * 100 / 255)
+}
+
+/// CIE Lab coordinates, with a D65 white point
+fn lab(pixel: Rgb<u8>) -> [f32; 3] {
+    let [r, g, b] = pixel.0.map(|c| {
+        let c = f32::from(c) / 255.;
+        if c > 0.040_45 {
+            ((c + 0.055) / 1.055).powf(2.4)
+        } else {
+            c / 12.92
+        }
+    });
+
+    let xyz = [
+        (0.412_4 * r + 0.357_6 * g + 0.180_5 * b) / 0.950_47,
+        0.212_6 * r + 0.715_2 * g + 0.072_2 * b,
+        (0.019_3 * r + 0.119_2 * g + 0.950_5 * b) / 1.088_83,
+    ];
+    let [fx, fy, fz] = xyz.map(|t| {
+        if t > 0.008_856 {
+            t.cbrt()
+        } else {
+            7.787 * t + 16. / 116.
+        }
+    });
+
+    [116. * fy - 16., 500. * (fx - fy), 200. * (fy - fz)]
+}
+
+/// Target color converted once to the space of its matcher
+#[derive(Clone, Copy)]
+enum Reference {
+    Rgb([u8; 3]),
+    /// Hue and saturation
+    Hsv(i32, i32),
+    Lab([f32; 3]),
+}
+
+/// Color target ready to be compared to many pixels
+#[derive(Clone, Copy)]
+pub struct PreparedTarget {
+    reference: Reference,
+    variation: u8,
+}
+
+impl PreparedTarget {
     #[must_use]
     pub fn matches(&self, pixel: &Rgb<u8>) -> bool {
-        let Rgb([tr, tg, tb]) = self.color;
-        let v = i16::from(self.variation);
+        let v = i32::from(self.variation);
+
+        match self.reference {
+            Reference::Rgb(color) => color
+                .iter()
+                .zip(pixel.0)
+                .all(|(&t, p)| i32::from(t.abs_diff(p)) <= v),
+            Reference::Hsv(target_hue, target_saturation) => {
+                let (hue, saturation, _) = hsv(*pixel);
+                let hue_distance = (target_hue - hue).abs().min(360 - (target_hue - hue).abs());
 
-        (i16::from(pixel[0]) - i16::from(tr)).abs() <= v
-            && (i16::from(pixel[1]) - i16::from(tg)).abs() <= v
-            && (i16::from(pixel[2]) - i16::from(tb)).abs() <= v
+                // Hue means nothing for greys
+                (saturation - target_saturation).abs() <= v
+                    && (target_saturation < 10 || hue_distance <= v)
+            }
+            Reference::Lab(target) => {
+                let distance = target
+                    .iter()
+                    .zip(lab(*pixel))
+                    .map(|(t, p)| (t - p).powi(2))
+                    .sum::<f32>()
+                    .sqrt();
+
+                distance <= f32::from(self.variation)
+            }
+        }
+    }
+}
+
+impl ColorTarget {
+    /// Convert the target color once, to compare it to many pixels
+    #[must_use]
+    pub fn prepare(&self) -> PreparedTarget {
+        let reference = match self.matcher {
+            Matcher::Rgb => Reference::Rgb(self.color.0),
+            Matcher::Hsv => {
+                let (hue, saturation, _) = hsv(self.color);
+                Reference::Hsv(hue, saturation)
+            }
+            Matcher::Lab => Reference::Lab(lab(self.color)),
+        };
+
+        PreparedTarget {
+            reference,
+            variation: self.variation,
+        }
+    }
+
+    /// Compare a single pixel, prefer [`Self::prepare`] for many of them
+    #[must_use]
+    pub fn matches(&self, pixel: &Rgb<u8>) -> bool {
+        self.prepare().matches(pixel)
     }
 
     /// Simple luminance approximation
@@ -0,0 +1,527 @@
+use std::{
+    ops::AddAssign,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use log::info;
+
+use crate::{
+    Stats,
+    utils::{
+        catch::Catch,
+        shake::{Bubble, BubbleTracker},
+    },
+};
+
+/// Steps of a fishing cycle
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FishingState {
+    /// Nothing happened yet
+    Idle,
+    /// Throwing the line
+    Casting,
+    /// Line is in the water, waiting for a shake or a bite
+    WaitingBite,
+    /// A shake bubble has been found
+    Shaking,
+    /// Playing the reel mini-game
+    Reeling,
+    /// The bite is over
+    Resolving,
+    /// A cast got no shake, make sure the rod is held
+    Recovering,
+}
+
+/// Time spent in a state
+#[derive(Clone, Copy, Default)]
+pub struct StateMetrics {
+    /// How many times we entered the state
+    pub visits: u64,
+    /// Total time spent in the state
+    pub time: Duration,
+}
+
+/// Everything the cycle needs to interact with the game
+pub trait Angler {
+    /// Throw the line
+    ///
+    /// # Return
+    /// Power of the cast, if the power bar was found
+    fn cast(&mut self) -> Option<u8>;
+
+    /// Returns a shake bubble, if any
+    fn find_shake(&mut self) -> Option<Bubble>;
+
+    /// Hit a shake bubble
+    ///
+    /// # Return
+    /// Number of bubbles hit, the following ones can be hit at once
+    fn shake(&mut self, bubble: &Bubble) -> u64;
+
+    /// Check if a fish bit
+    fn hooked(&mut self) -> bool;
+
+    /// Play one step of the reel mini-game
+    ///
+    /// # Return
+    /// `false` once the bite is over
+    fn reel(&mut self) -> bool;
+
+    /// Called once the bite is over, before casting again
+    ///
+    /// # Return
+    /// The fish caught, if it could be identified
+    fn resolve(&mut self) -> Option<Catch>;
+
+    /// Check if the inventory is full, reacting to it
+    fn inventory_full(&mut self) -> bool;
+
+    /// Send some input, so the game doesn't think we are idle
+    fn keep_alive(&mut self);
+
+    /// Check that the character is still where it was fishing, getting back in position otherwise
+    ///
+    /// # Return
+    /// `true` if the scene changed
+    fn check_scene(&mut self) -> bool;
+
+    /// Walk back to the fishing spot if the character drifted away
+    ///
+    /// # Return
+    /// `true` if the position was corrected
+    fn check_drift(&mut self) -> bool;
+
+    /// Make sure the rod is held, checking the hotbar before selecting it
+    fn recover(&mut self);
+
+    /// Dismiss anything covering the game
+    ///
+    /// # Return
+    /// Names of the overlays dismissed or seen since the last call
+    fn tidy_up(&mut self) -> Vec<&'static str>;
+}
+
+/// Limits of the cycle
+pub struct Timeouts {
+    /// Time without any shake before casting again
+    pub shake: Duration,
+    /// Maximum shake count before casting again
+    pub max_shake_count: u8,
+    /// Already hit bubbles found in a row before checking the UI
+    pub max_duplicate_shakes: u8,
+    /// Time between two UI checks
+    pub hygiene: Duration,
+    /// Time between two keep-alive inputs
+    pub keep_alive: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            shake: Duration::from_secs(5),
+            max_shake_count: 40,
+            max_duplicate_shakes: 5,
+            hygiene: Duration::from_mins(1),
+            keep_alive: Duration::from_mins(5),
+        }
+    }
+}
+
+/// State machine of the fishing process
+pub struct FishingCycle {
+    state: FishingState,
+    entered_at: Instant,
+    timeouts: Timeouts,
+
+    last_shake_time: Instant,
+    shake_count: u8,
+    pending_shake: Option<Bubble>,
+    /// Bubbles hit during the current cast
+    bubbles: BubbleTracker,
+    /// Shakes done during the current cast
+    cast_shakes: u64,
+    /// Already hit bubbles found in a row
+    duplicate_shakes: u8,
+
+    last_hygiene: Instant,
+    /// Detection got weird results, the UI may be polluted
+    anomaly: bool,
+    last_keep_alive: Instant,
+}
+
+impl FishingCycle {
+    #[must_use]
+    pub fn new(timeouts: Timeouts) -> Self {
+        let now = Instant::now();
+        Self {
+            state: FishingState::Idle,
+            entered_at: now,
+            timeouts,
+            last_shake_time: now,
+            shake_count: 0,
+            pending_shake: None,
+            bubbles: BubbleTracker::default(),
+            cast_shakes: 0,
+            duplicate_shakes: 0,
+            last_hygiene: now,
+            anomaly: false,
+            last_keep_alive: now,
+        }
+    }
+
+    #[must_use]
+    pub fn state(&self) -> FishingState {
+        self.state
+    }
+
+    /// Move to another state, recording the time spent in the current one
+    fn transition(&mut self, next: FishingState, stats: &mut Stats) {
+        stats.record_state(self.state, self.entered_at.elapsed());
+        info!("{:?} -> {next:?}", self.state);
+        self.state = next;
+        self.entered_at = Instant::now();
+    }
+
+    /// Run one step of the cycle
+    pub fn step(&mut self, angler: &mut impl Angler, stats: &mut Stats) {
+        // Don't interrupt the mini-game
+        if self.state != FishingState::Reeling
+            && (self.anomaly || self.last_hygiene.elapsed() > self.timeouts.hygiene)
+        {
+            stats.add_ui_check(&angler.tidy_up());
+            self.anomaly = false;
+            self.last_hygiene = Instant::now();
+        }
+
+        match self.state {
+            FishingState::Idle => self.transition(FishingState::Casting, stats),
+            FishingState::Casting => {
+                stats.add_cast_shakes(self.cast_shakes);
+
+                if angler.check_scene() {
+                    stats.scene_changes.add_assign(1);
+                } else if angler.check_drift() {
+                    stats.drift_corrections.add_assign(1);
+                }
+
+                // The line is out of the water, we can move
+                if self.last_keep_alive.elapsed() > self.timeouts.keep_alive {
+                    angler.keep_alive();
+                    self.last_keep_alive = Instant::now();
+                }
+
+                let power = angler.cast();
+                stats.add_cast(power);
+                stats.add_reel();
+
+                self.bubbles.clear();
+                self.cast_shakes = 0;
+                self.duplicate_shakes = 0;
+                self.shake_count = 0;
+                self.last_shake_time = Instant::now();
+                self.transition(FishingState::WaitingBite, stats);
+            }
+            FishingState::WaitingBite => {
+                if let Some(bubble) = angler.find_shake().filter(|bubble| {
+                    let duplicate = self.bubbles.already_hit(bubble);
+                    if duplicate {
+                        stats.duplicate_shakes.add_assign(1);
+                        // A bubble fading out is expected, something stuck on screen isn't
+                        self.duplicate_shakes = self.duplicate_shakes.saturating_add(1);
+                        self.anomaly |= self.duplicate_shakes >= self.timeouts.max_duplicate_shakes;
+                    } else {
+                        self.duplicate_shakes = 0;
+                    }
+                    !duplicate
+                }) {
+                    self.pending_shake = Some(bubble);
+                    self.transition(FishingState::Shaking, stats);
+                } else if self.last_shake_time.elapsed() > self.timeouts.shake {
+                    if self.cast_shakes == 0 {
+                        // A whole cast without any shake, the rod may not be held
+                        self.anomaly = true;
+                        self.transition(FishingState::Recovering, stats);
+                    } else {
+                        self.transition(FishingState::Casting, stats);
+                    }
+                } else if angler.hooked() {
+                    self.transition(FishingState::Reeling, stats);
+                }
+            }
+            FishingState::Shaking => {
+                if let Some(bubble) = self.pending_shake.take() {
+                    let hits = angler.shake(&bubble);
+                    self.bubbles.hit(&bubble);
+                    self.cast_shakes += hits;
+                    stats.shakes.add_assign(hits);
+                }
+
+                // Too much tries
+                if self.shake_count > self.timeouts.max_shake_count {
+                    self.transition(FishingState::Casting, stats);
+                } else {
+                    self.shake_count += 1;
+                    self.last_shake_time = Instant::now();
+                    self.transition(FishingState::WaitingBite, stats);
+                }
+            }
+            FishingState::Reeling => {
+                if !angler.reel() {
+                    stats.add_fishing_time(self.entered_at.elapsed().as_secs());
+                    self.transition(FishingState::Resolving, stats);
+                }
+            }
+            FishingState::Resolving => {
+                if let Some(catch) = angler.resolve() {
+                    stats.record_catch(catch);
+                }
+                if angler.inventory_full() {
+                    stats.inventory_full.add_assign(1);
+                }
+                self.transition(FishingState::Casting, stats);
+            }
+            FishingState::Recovering => {
+                angler.recover();
+                self.transition(FishingState::Casting, stats);
+            }
+        }
+    }
+
+    /// Run the cycle until `cond` is set
+    pub fn run(&mut self, angler: &mut impl Angler, stats: &mut Stats, cond: &AtomicBool) {
+        while !cond.load(Ordering::Relaxed) {
+            self.step(angler, stats);
+        }
+        stats.add_cast_shakes(self.cast_shakes);
+        stats.record_state(self.state, self.entered_at.elapsed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::utils::geometry::{Point, Region};
+
+    /// Scripted game
+    #[derive(Default)]
+    struct MockAngler {
+        /// Bubbles returned by `find_shake`, last first
+        bubbles: Vec<Bubble>,
+        hooked: bool,
+        /// Reel steps before the bite is over
+        reel_steps: u8,
+        casts: u8,
+        shakes: u8,
+        recoveries: u8,
+        resolved: u8,
+        tidy_ups: u8,
+    }
+
+    impl Angler for MockAngler {
+        fn cast(&mut self) -> Option<u8> {
+            self.casts += 1;
+            Some(100)
+        }
+
+        fn find_shake(&mut self) -> Option<Bubble> {
+            self.bubbles.pop()
+        }
+
+        fn shake(&mut self, _: &Bubble) -> u64 {
+            self.shakes += 1;
+            1
+        }
+
+        fn hooked(&mut self) -> bool {
+            self.hooked
+        }
+
+        fn reel(&mut self) -> bool {
+            self.reel_steps = self.reel_steps.saturating_sub(1);
+            self.reel_steps > 0
+        }
+
+        fn resolve(&mut self) -> Option<Catch> {
+            self.resolved += 1;
+            None
+        }
+
+        fn inventory_full(&mut self) -> bool {
+            false
+        }
+
+        fn keep_alive(&mut self) {}
+
+        fn check_scene(&mut self) -> bool {
+            false
+        }
+
+        fn check_drift(&mut self) -> bool {
+            false
+        }
+
+        fn recover(&mut self) {
+            self.recoveries += 1;
+        }
+
+        fn tidy_up(&mut self) -> Vec<&'static str> {
+            self.tidy_ups += 1;
+            Vec::new()
+        }
+    }
+
+    fn bubble(x: u32) -> Bubble {
+        Bubble {
+            center: Point { x, y: 100 },
+            bounds: Region {
+                point1: Point { x: x - 10, y: 90 },
+                point2: Point { x: x + 10, y: 110 },
+            },
+            score: 100,
+        }
+    }
+
+    /// Cycle which just cast the line
+    fn waiting(timeouts: Timeouts, angler: &mut MockAngler, stats: &mut Stats) -> FishingCycle {
+        let mut cycle = FishingCycle::new(timeouts);
+        cycle.step(angler, stats);
+        cycle.step(angler, stats);
+        assert_eq!(cycle.state(), FishingState::WaitingBite);
+        assert_eq!(angler.casts, 1);
+
+        cycle
+    }
+
+    #[test]
+    fn shake_timeout_casts_again() {
+        let (mut angler, mut stats) = (MockAngler::default(), Stats::new(true));
+        let timeouts = Timeouts {
+            shake: Duration::ZERO,
+            ..Default::default()
+        };
+        let mut cycle = waiting(timeouts, &mut angler, &mut stats);
+        angler.bubbles = vec![bubble(100)];
+
+        cycle.step(&mut angler, &mut stats);
+        cycle.step(&mut angler, &mut stats);
+        assert_eq!(cycle.state(), FishingState::WaitingBite);
+
+        thread::sleep(Duration::from_millis(1));
+        cycle.step(&mut angler, &mut stats);
+        assert_eq!(cycle.state(), FishingState::Casting);
+
+        cycle.step(&mut angler, &mut stats);
+        assert_eq!(angler.casts, 2);
+        assert_eq!(*stats.reels, 2);
+        assert_eq!(angler.recoveries, 0);
+    }
+
+    #[test]
+    fn cast_without_shakes_recovers() {
+        let (mut angler, mut stats) = (MockAngler::default(), Stats::new(true));
+        let timeouts = Timeouts {
+            shake: Duration::ZERO,
+            ..Default::default()
+        };
+        let mut cycle = waiting(timeouts, &mut angler, &mut stats);
+
+        thread::sleep(Duration::from_millis(1));
+        cycle.step(&mut angler, &mut stats);
+        assert_eq!(cycle.state(), FishingState::Recovering);
+
+        cycle.step(&mut angler, &mut stats);
+        assert_eq!(angler.recoveries, 1);
+        assert_eq!(cycle.state(), FishingState::Casting);
+    }
+
+    #[test]
+    fn waiting_for_the_bite_is_normal() {
+        let (mut angler, mut stats) = (MockAngler::default(), Stats::new(true));
+        let mut cycle = waiting(Timeouts::default(), &mut angler, &mut stats);
+
+        for _ in 0..100 {
+            cycle.step(&mut angler, &mut stats);
+            assert_eq!(cycle.state(), FishingState::WaitingBite);
+        }
+        assert_eq!(angler.recoveries, 0);
+        assert_eq!(angler.tidy_ups, 0);
+    }
+
+    #[test]
+    fn too_many_shakes_cast_again() {
+        let (mut angler, mut stats) = (MockAngler::default(), Stats::new(true));
+        let timeouts = Timeouts {
+            max_shake_count: 2,
+            ..Default::default()
+        };
+        let mut cycle = waiting(timeouts, &mut angler, &mut stats);
+        angler.bubbles = (1..=4).map(|i| bubble(i * 100)).collect();
+
+        for _ in 0..3 {
+            cycle.step(&mut angler, &mut stats);
+            assert_eq!(cycle.state(), FishingState::Shaking);
+            cycle.step(&mut angler, &mut stats);
+            assert_eq!(cycle.state(), FishingState::WaitingBite);
+        }
+        cycle.step(&mut angler, &mut stats);
+        cycle.step(&mut angler, &mut stats);
+        assert_eq!(cycle.state(), FishingState::Casting);
+        assert_eq!(angler.shakes, 4);
+        assert_eq!(*stats.shakes, 4);
+    }
+
+    #[test]
+    fn reeling_resolves() {
+        let (mut angler, mut stats) = (MockAngler::default(), Stats::new(true));
+        let mut cycle = waiting(Timeouts::default(), &mut angler, &mut stats);
+        angler.hooked = true;
+        angler.reel_steps = 2;
+
+        cycle.step(&mut angler, &mut stats);
+        assert_eq!(cycle.state(), FishingState::Reeling);
+        cycle.step(&mut angler, &mut stats);
+        assert_eq!(cycle.state(), FishingState::Reeling);
+        cycle.step(&mut angler, &mut stats);
+        assert_eq!(cycle.state(), FishingState::Resolving);
+        assert_eq!(*stats.fishes, 1);
+
+        cycle.step(&mut angler, &mut stats);
+        assert_eq!(angler.resolved, 1);
+        assert_eq!(cycle.state(), FishingState::Casting);
+    }
+
+    #[test]
+    fn disabled_stats_dont_count_reels() {
+        let (mut angler, mut stats) = (MockAngler::default(), Stats::new(false));
+        waiting(Timeouts::default(), &mut angler, &mut stats);
+
+        assert_eq!(*stats.reels, 0);
+    }
+
+    #[test]
+    fn repeated_duplicates_check_the_ui() {
+        let (mut angler, mut stats) = (MockAngler::default(), Stats::new(true));
+        let timeouts = Timeouts {
+            max_duplicate_shakes: 3,
+            ..Default::default()
+        };
+        let mut cycle = waiting(timeouts, &mut angler, &mut stats);
+        angler.bubbles = vec![bubble(100); 4];
+
+        cycle.step(&mut angler, &mut stats);
+        cycle.step(&mut angler, &mut stats);
+        assert_eq!(angler.shakes, 1);
+
+        // A single duplicate is a bubble fading out
+        for _ in 0..3 {
+            cycle.step(&mut angler, &mut stats);
+            assert_eq!(angler.tidy_ups, 0);
+        }
+        cycle.step(&mut angler, &mut stats);
+        assert_eq!(angler.tidy_ups, 1);
+        assert_eq!(*stats.duplicate_shakes, 3);
+    }
+}
@@ -1,16 +1,59 @@
 use std::{
+    fs, io,
     ops::{Deref, DerefMut},
+    path::Path,
     slice,
 };
 
 use image::{Rgb, RgbImage};
-use log::debug;
+use log::{debug, info};
 
 use crate::utils::{
-    colors::ColorTarget,
-    geometry::{Point, Region},
+    calibration::{Calibration, Palette},
+    colors::{ColorTarget, Matcher},
+    geometry::{Dimensions, Point, Region},
 };
 
+/// Number of rows sampled in the mini-game
+const SCANLINES: u32 = 5;
+
+/// Minimum percentage of scanlines that have to agree on an observation
+const MIN_CONFIDENCE: u8 = 40;
+
+/// Width of the mini-game frame around the bar, in pixels
+const FRAME_BORDER: u32 = 20;
+
+/// How far from the frame edges the arrows are searched, in pixels
+const ARROWS_REACH: u32 = 30;
+
+/// Control-arrows on both sides of the mini-game
+const ARROWS: ColorTarget = ColorTarget {
+    color: Rgb([0x5f, 0x3b, 0x34]),
+    variation: 4,
+    matcher: Matcher::Rgb,
+};
+
+/// Fuse observations made on several scanlines
+///
+/// # Return
+/// Couple (median value, percentage of observations close to the median)
+fn fuse(values: &[Option<u32>], tolerance: u32) -> Option<(u32, u8)> {
+    let mut found = values.iter().flatten().copied().collect::<Vec<_>>();
+    if found.is_empty() {
+        return None;
+    }
+    found.sort_unstable();
+    let median = found[found.len() / 2];
+
+    let agreeing = found
+        .iter()
+        .filter(|v| v.abs_diff(median) <= tolerance)
+        .count();
+    let confidence = u8::try_from(agreeing * 100 / values.len()).unwrap_or(100);
+
+    Some((median, confidence))
+}
+
 #[derive(Clone)]
 pub struct HookPosition {
     /// Left side position
@@ -32,30 +75,59 @@ pub struct Hook {
     /// If the fish is currently on the hook
     pub fish_on: bool,
 
+    /// Percentage of scanlines agreeing on the hook position
+    pub confidence: u8,
+
     /// Last known position of a fish
     last_fish_position: Option<Point>,
 }
 
-pub enum FishPosition {
-    Exact(Point),
-    Approximative(Point),
+impl Hook {
+    /// If the hook position can be trusted on this frame
+    #[must_use]
+    pub fn reliable(&self) -> bool {
+        self.fish_on && self.position.is_some() && self.confidence >= MIN_CONFIDENCE
+    }
+}
+
+pub struct FishPosition {
+    pub point: Point,
+    /// Percentage of scanlines agreeing on the fish position,
+    /// when too low this is the last known position (the fish is probably getting slashed)
+    pub confidence: u8,
+}
+
+impl FishPosition {
+    /// If the position can be trusted on this frame
+    #[must_use]
+    pub fn reliable(&self) -> bool {
+        self.confidence >= MIN_CONFIDENCE
+    }
 }
 
 pub struct Rod {
     internals: Hook,
+
+    /// Learn the mini-game colors during the first frames
+    calibration: Calibration,
+    /// Colors learned by the calibration
+    palette: Option<Palette>,
 }
 
 impl Rod {
     #[must_use]
     pub fn new(screen: &RgbImage, mini_game_region: &Region) -> Self {
-        match Self::search_hook(screen, mini_game_region) {
-            Some((length, position)) => Rod {
+        match Self::search_hook(screen, mini_game_region, None) {
+            Some((length, position, confidence)) => Rod {
                 internals: Hook {
                     position: Some(position),
                     length,
                     fish_on: false,
+                    confidence,
                     last_fish_position: None,
                 },
+                calibration: Calibration::default(),
+                palette: None,
             },
             None => Rod {
                 internals: {
@@ -65,9 +137,12 @@ impl Rod {
                         position: None,
                         length: (mini_game_region.get_size().width * percentage / 100),
                         fish_on: false,
+                        confidence: 0,
                         last_fish_position: None,
                     }
                 },
+                calibration: Calibration::default(),
+                palette: None,
             },
         }
     }
@@ -75,11 +150,60 @@ impl Rod {
     /// Find the hook
     ///
     /// # Return
-    /// Couple (hook's length, hook's position)
-    fn search_hook(screen: &RgbImage, region: &Region) -> Option<(u32, HookPosition)> {
-        let [x_min, y_min, x_max, y_max] = region.corners();
-        let y = y_min.midpoint(y_max);
+    /// Triple (hook's length, hook's position, confidence)
+    fn search_hook(
+        screen: &RgbImage,
+        region: &Region,
+        palette: Option<&Palette>,
+    ) -> Option<(u32, HookPosition, u8)> {
+        let [x_min, _, x_max, _] = region.corners();
+
+        let segments = region
+            .scanlines(SCANLINES)
+            .into_iter()
+            .map(|y| Self::search_hook_row(screen, x_min, x_max, y, palette))
+            .collect::<Vec<_>>();
+
+        // TODO: Scale with screen resolution?
+        let tolerance = 10;
+        let (l, l_confidence) = fuse(
+            &segments
+                .iter()
+                .map(|s| s.map(|(l, _)| l))
+                .collect::<Vec<_>>(),
+            tolerance,
+        )?;
+        let (r, r_confidence) = fuse(
+            &segments
+                .iter()
+                .map(|s| s.map(|(_, r)| r))
+                .collect::<Vec<_>>(),
+            tolerance,
+        )?;
+
+        let width = r.saturating_sub(l);
+        Some((
+            width,
+            HookPosition {
+                absolute_beg_x: l,
+                absolute_mid_x: l + width / 2,
+                absolute_end_x: r,
+            },
+            l_confidence.min(r_confidence),
+        ))
+    }
 
+    /// Find the hook on a single row
+    ///
+    /// # Return
+    /// Couple (left side, right side)
+    fn search_hook_row(
+        screen: &RgbImage,
+        x_min: u32,
+        x_max: u32,
+        y: u32,
+        palette: Option<&Palette>,
+    ) -> Option<(u32, u32)> {
         // TODO: Scale with screen resolution?
         let gap_tolerance = 35; // take into account arrows and fish that overlap the hook bar
 
@@ -87,7 +211,12 @@ impl Rod {
             .map(|x| (x, ColorTarget::brightness(screen.get_pixel(x, y))))
             .collect::<Vec<_>>();
 
-        let threshold = {
+        let learned = palette.and_then(|p| p.hook.as_ref().map(|hook| (hook, &p.background)));
+        let threshold = if let Some((hook, background)) = learned {
+            // Halfway between the learned colors, leaving room for the bar gradient
+            ColorTarget::brightness(&background.color)
+                .midpoint(ColorTarget::brightness(&hook.color))
+        } else {
             let mut sorted = brightnesses.iter().map(|(_, b)| *b).collect::<Vec<_>>();
             sorted.sort_unstable();
             // We use 3% because it's probably the smallest the hook bar can get
@@ -127,30 +256,23 @@ impl Rod {
                 let fish_cursor_size = 20;
                 (l - fish_cursor_size, r - fish_cursor_size)
             })
-            // Compute width
-            .map(|(l, r)| (r - l, l, r))
             // Keep longest segment
-            .max_by_key(|(width, _, _)| *width)
-            // INFO: we could map only once and create directly our structure,
-            //       but since we can get MANY segments (like ~400),
-            //       we only do that for the chosen one
-            .map(|(width, l, r)| {
-                (
-                    width,
-                    HookPosition {
-                        absolute_beg_x: l,
-                        absolute_mid_x: l + width / 2,
-                        absolute_end_x: r,
-                    },
-                )
-            })
+            .max_by_key(|(l, r)| r - l)
     }
 
     /// Refresh hook data
     fn update_hook(&mut self, image: &RgbImage, mini_game_region: &Region) {
-        let hook_data = Self::search_hook(image, mini_game_region);
+        if self.palette.is_none() {
+            self.palette = self.calibration.observe(image, mini_game_region);
+        }
+
+        let hook_data = Self::search_hook(image, mini_game_region, self.palette.as_ref());
         self.internals.fish_on = hook_data.is_some();
-        if let Some((l, hook_pos)) = hook_data {
+        self.internals.confidence = hook_data.as_ref().map_or(0, |(_, _, c)| *c);
+        // A splash can hide a few scanlines, but not most of them
+        if let Some((l, hook_pos, _)) =
+            hook_data.filter(|(_, _, confidence)| *confidence >= MIN_CONFIDENCE)
+        {
             self.internals.length = l;
             self.internals.position = Some(hook_pos);
         }
@@ -178,43 +300,128 @@ impl Rod {
         // Here we could store the fish position that we deduce from segments sizes in `search_hook`
         // Instead of relying on fish_color
 
-        let fish_color = &[
+        let default_fish_color = [
             ColorTarget {
                 color: Rgb([0x43, 0x4b, 0x5b]),
                 variation: 3,
+                matcher: Matcher::Rgb,
             },
             ColorTarget {
                 color: Rgb([0x4a, 0x4a, 0x5c]),
                 variation: 4,
+                matcher: Matcher::Rgb,
             },
             ColorTarget {
                 color: Rgb([0x47, 0x51, 0x5d]),
                 variation: 4,
+                matcher: Matcher::Rgb,
             },
         ];
+        let fish_color = match self.palette.as_ref().and_then(|p| p.fish.as_ref()) {
+            Some(learned) => slice::from_ref(learned),
+            None => &default_fish_color,
+        };
+
+        // TODO: Scale with screen resolution?
+        let tolerance = 10;
+        let positions = mini_game_region
+            .scanlines(SCANLINES)
+            .into_iter()
+            .map(|y| mini_game_region.search_color_row_ltr(image, fish_color, y))
+            .collect::<Vec<_>>();
+
+        let observation = fuse(
+            &positions
+                .iter()
+                .map(|p| p.as_ref().map(|p| p.x))
+                .collect::<Vec<_>>(),
+            tolerance,
+        );
+        let confidence = observation.map_or(0, |(_, confidence)| confidence);
 
-        match mini_game_region.search_color_mid_ltr(image, fish_color) {
+        match observation.filter(|(_, confidence)| *confidence >= MIN_CONFIDENCE) {
             // Return fresh fish position
-            Some(pos) => {
-                self.internals.last_fish_position = Some(pos.clone());
-                Some(FishPosition::Exact(pos))
+            Some((x, _)) => {
+                let point = Point {
+                    x,
+                    y: mini_game_region
+                        .point1
+                        .y
+                        .midpoint(mini_game_region.point2.y),
+                };
+                self.internals.last_fish_position = Some(point.clone());
+                Some(FishPosition { point, confidence })
             }
             // Return latest known position (the fish is probably getting slashed)
             None => self
                 .internals
                 .last_fish_position
                 .as_ref()
-                .map(|p| FishPosition::Approximative(p.clone())),
+                .map(|p| FishPosition {
+                    point: p.clone(),
+                    confidence,
+                }),
         }
     }
 }
 
+/// Decide if a fish bit from successive observations of the mini-game bar
+#[derive(Default)]
+pub struct BiteDetector {
+    /// Smoothed confidence percentage
+    confidence: u8,
+    /// Current decision
+    hooked: bool,
+}
+
+impl BiteDetector {
+    /// Confidence needed to consider a fish hooked
+    const ON: u8 = 60;
+    /// Confidence under which the bite is over
+    const OFF: u8 = 30;
+
+    /// Add an observation
+    ///
+    /// # Return
+    /// If a fish is hooked
+    pub fn update(&mut self, frame_confidence: u8) -> bool {
+        self.confidence = self.confidence.midpoint(frame_confidence);
+
+        // Hysteresis, so a single bad frame doesn't flip the decision
+        self.hooked = if self.hooked {
+            self.confidence > Self::OFF
+        } else {
+            self.confidence >= Self::ON
+        };
+
+        self.hooked
+    }
+
+    #[must_use]
+    pub fn confidence(&self) -> u8 {
+        self.confidence
+    }
+
+    /// Forget the previous observations, once a bite is over
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
 /// Enhanced region
 pub struct MiniGame {
     /// Mini-game bar
     outer: Region,
+    /// Area where the mini-game can show up, before refining
+    default: Region,
     /// Rod bar
     pub rod: Option<Rod>,
+    /// If the area has been refined
+    pub refined: bool,
+    /// If the area comes from a previous session and hasn't been checked yet
+    restored: bool,
+    /// Bite detection state
+    bite: BiteDetector,
 }
 
 impl Deref for MiniGame {
@@ -235,79 +442,252 @@ impl MiniGame {
     #[must_use]
     pub fn new(region: Region) -> MiniGame {
         MiniGame {
+            default: region.clone(),
             outer: region,
             rod: None,
+            refined: false,
+            restored: false,
+            bite: BiteDetector::default(),
         }
     }
 
-    /// Search if the fish is hooked based on the mouse above the minigame
-    #[must_use]
-    pub fn any_fish_hooked(&self, screen: &RgbImage) -> bool {
-        // We use multiple scanlines, to be more resilient in the case of a slash above the mouse
-        let (x_min, x_max) = {
-            let half = screen.width() / 2;
-            (half + 5, half + 7)
-        };
+    /// Search if a fish is hooked based on the mini-game bar being visible
+    pub fn bite_detected(&mut self, screen: &RgbImage) -> bool {
+        let mut confidence = Self::search_structure(screen, &self.outer).map_or(0, |(_, c)| c);
 
-        // Large height to accomadate many screen dispositions
-        let (y_min, y_max) = (screen.height() * 70 / 100, screen.height() * 80 / 100);
+        // A stale restored area would miss the mini-game
+        if self.restored {
+            confidence =
+                confidence.max(Self::search_structure(screen, &self.default).map_or(0, |(_, c)| c));
+        }
 
-        #[cfg(feature = "imageproc")]
-        {
-            use crate::{Point, utils::debug::Drawable};
-            use std::sync::Arc;
+        self.bite.update(confidence)
+    }
 
-            Region {
-                point1: Point { x: x_min, y: y_min },
-                point2: Point { x: x_max, y: y_max },
-            }
-            .draw_async(Arc::new(screen.clone()), "mouses/0.png", false);
-        }
+    /// Returns the confidence percentage of the bite detection
+    #[must_use]
+    pub fn bite_confidence(&self) -> u8 {
+        self.bite.confidence()
+    }
 
-        let consecutive = 20;
-        (x_min..=x_max)
-            .rev() // 7 → 6 → 5 → 4
-            .any(|x| {
-                (y_min..=y_max.saturating_sub(consecutive - 1)).any(|y_start| {
-                    let Rgb([r1, g1, b1]) = *screen.get_pixel(x, y_start);
-                    (0..consecutive).all(|i| {
-                        let Rgb([r2, g2, b2]) = *screen.get_pixel(x, y_start + i);
-                        let tolerance = 3;
-                        let brightness = ColorTarget::brightness(screen.get_pixel(x, y_start + i));
-                        // Bright/Dark mouse
-                        let correct_brightness =
-                            (210..240).contains(&brightness) || (90..120).contains(&brightness);
-                        correct_brightness
-                            && (i16::from(r1) - i16::from(r2)).abs() <= tolerance
-                            && (i16::from(g1) - i16::from(g2)).abs() <= tolerance
-                            && (i16::from(b1) - i16::from(b2)).abs() <= tolerance
-                    })
-                })
-            })
+    /// Start the bite detection over, so the previous bite doesn't count for the next cast
+    pub fn reset_bite(&mut self) {
+        self.bite.reset();
     }
 
     /// This HAS to be called at the very beginning of the fishing process
     /// It refine the global mini-game area to precisely it coordinates
     /// This shouldn't change accross hooks
     ///
+    /// # Return
+    /// Confidence percentage of the refined area
+    ///
+    /// # Errors
+    /// If neither the control-arrows nor the bar structure were found
+    pub fn refine_area(&mut self, img: &RgbImage) -> Result<u8, String> {
+        let (region, confidence) = match self.search_arrows(img) {
+            Ok(region) => (region, 100),
+            Err(e) => {
+                info!("{e}, falling back to the bar structure");
+                Self::search_structure(img, &self.outer)
+                    .filter(|(_, confidence)| *confidence >= 50)
+                    .ok_or("Couldn't find the mini-game bar")?
+            }
+        };
+
+        self.outer = region;
+        self.refined = true;
+
+        Ok(confidence)
+    }
+
+    /// Locate the mini-game from its control-arrows
+    ///
     /// # Errors
     /// If no control-arrows found
-    pub fn refine_area(&mut self, img: &RgbImage) -> Result<(), String> {
+    fn search_arrows(&self, img: &RgbImage) -> Result<Region, String> {
         // Attempt to find arrows in both halves
-        let color = slice::from_ref(&ColorTarget {
-            color: Rgb([0x5f, 0x3b, 0x34]),
-            variation: 4,
-        });
+        let color = slice::from_ref(&ARROWS);
         let (left, right) = self
             .search_color_left_half(img, color)
             .zip(self.search_color_right_half(img, color))
             .ok_or("Couldn't find arrows")?;
 
         // Update points with offsets
-        self.point1 = left + (20, -10);
-        self.point2 = right + (-20, 20);
+        Ok(Region {
+            point1: left + (20, -10),
+            point2: right + (-20, 20),
+        })
+    }
+
+    /// Locate the mini-game from its long dark frame and the bright hook bar inside it
+    ///
+    /// # Return
+    /// Couple (refined region, confidence percentage)
+    fn search_structure(img: &RgbImage, area: &Region) -> Option<(Region, u8)> {
+        let [x_min, y_min, x_max, y_max] = area.corners();
+        let width = x_max - x_min;
+        let is_dark = |x, y| ColorTarget::brightness(img.get_pixel(x, y)) < 90;
+
+        // Rows crossed by the frame: dark pixels over most of the width,
+        // the hook bar can hide a part of it
+        let rows = (y_min..=y_max)
+            .map(|y| {
+                let first = (x_min..=x_max).find(|&x| is_dark(x, y))?;
+                let last = (x_min..=x_max).rev().find(|&x| is_dark(x, y))?;
+                let dark = (first..=last).filter(|&x| is_dark(x, y)).count();
+
+                (last - first >= width * 60 / 100 && dark * 2 >= (last - first) as usize)
+                    .then_some((first, last))
+            })
+            .collect::<Vec<_>>();
+
+        // Keep the tallest band of consecutive rows
+        let (top, height) = rows
+            .iter()
+            .enumerate()
+            .scan(None, |start, (i, row)| {
+                *start = row.map(|_| start.unwrap_or(i));
+                Some(start.map(|s| (s, i - s + 1)))
+            })
+            .flatten()
+            .max_by_key(|(_, height)| *height)?;
+        let band = rows[top..top + height].iter().flatten().collect::<Vec<_>>();
+
+        let median = |mut v: Vec<u32>| {
+            v.sort_unstable();
+            v[v.len() / 2]
+        };
+        let left = median(band.iter().map(|(l, _)| *l).collect());
+        let right = median(band.iter().map(|(_, r)| *r).collect());
+        // Too narrow to hold the bar inside its borders
+        if right < left + 2 * FRAME_BORDER + 1 {
+            return None;
+        }
+
+        // TODO: Scale with screen resolution?
+        let tolerance = 10;
+        let consistent = band
+            .iter()
+            .filter(|(l, r)| l.abs_diff(left) <= tolerance && r.abs_diff(right) <= tolerance)
+            .count();
+
+        let region = Region {
+            point1: Point {
+                x: left + FRAME_BORDER,
+                y: y_min + u32::try_from(top).ok()?,
+            },
+            point2: Point {
+                x: right - FRAME_BORDER,
+                y: y_min + u32::try_from(top + height).ok()?,
+            },
+        };
+
+        // Dark water looks like the frame, only the bright hook bar or the arrows tell them apart
+        let y = region.point1.y.midpoint(region.point2.y);
+        let hook_found = (left..=right)
+            .map(|x| ColorTarget::brightness(img.get_pixel(x, y)) > 180)
+            .fold((0, 0), |(run, longest), bright| {
+                let run = if bright { run + 1 } else { 0 };
+                (run, longest.max(run))
+            })
+            .1
+            >= (right - left) * 5 / 100;
+        let arrows = ARROWS.prepare();
+        let last_x = img.width() - 1;
+        let arrows_found = [left, right]
+            .map(|x| x.saturating_sub(ARROWS_REACH)..=(x + ARROWS_REACH).min(last_x))
+            .into_iter()
+            .all(|mut xs| {
+                xs.any(|x| {
+                    (region.point1.y..region.point2.y).any(|y| arrows.matches(img.get_pixel(x, y)))
+                })
+            });
+        if !hook_found && !arrows_found {
+            return None;
+        }
+
+        let confidence = consistent * 70 / band.len() + 30;
+
+        #[cfg(feature = "imageproc")]
+        {
+            use crate::utils::debug::Drawable;
+            use std::sync::Arc;
+
+            region
+                .clone()
+                .draw_async(Arc::new(img.clone()), "mini_game_structure.png", true);
+        }
+
+        Some((region, u8::try_from(confidence).unwrap_or(100)))
+    }
+
+    /// Save the refined area, so it can be reused by the next sessions
+    ///
+    /// # Errors
+    /// If the file couldn't be written
+    pub fn save(&self, path: impl AsRef<Path>, screen: &Dimensions) -> io::Result<()> {
+        let [x_min, y_min, x_max, y_max] = self.corners();
+        fs::write(
+            path,
+            format!(
+                "{} {} {x_min} {y_min} {x_max} {y_max}",
+                screen.width, screen.height
+            ),
+        )
+    }
+
+    /// Restore an area saved by a previous session on the same screen
+    ///
+    /// # Return
+    /// If the area has been restored
+    pub fn restore(&mut self, path: impl AsRef<Path>, screen: &Dimensions) -> bool {
+        let Some([width, height, x_min, y_min, x_max, y_max]) = fs::read_to_string(path)
+            .ok()
+            .and_then(|content| {
+                content
+                    .split_whitespace()
+                    .map(str::parse)
+                    .collect::<Result<Vec<u32>, _>>()
+                    .ok()
+            })
+            .and_then(|values| <[u32; 6]>::try_from(values).ok())
+        else {
+            return false;
+        };
+
+        if width != screen.width || height != screen.height {
+            return false;
+        }
+
+        self.outer = Region {
+            point1: Point { x: x_min, y: y_min },
+            point2: Point { x: x_max, y: y_max },
+        };
+        self.refined = true;
+        self.restored = true;
 
-        Ok(())
+        true
+    }
+
+    /// Check the restored area on the first bite, it is dropped if the mini-game isn't there
+    ///
+    /// # Return
+    /// If the area is still valid
+    pub fn verify(&mut self, screen: &RgbImage) -> bool {
+        if !self.restored {
+            return true;
+        }
+        self.restored = false;
+
+        let valid = Self::search_structure(screen, &self.outer)
+            .is_some_and(|(_, confidence)| confidence >= 50);
+        if !valid {
+            self.outer = self.default.clone();
+            self.refined = false;
+        }
+
+        valid
     }
 
     pub fn initialize_rod(&mut self, rod: Rod) {
@@ -329,18 +709,15 @@ impl MiniGame {
     ///
     /// # Panics
     /// If there is no rod stored
-    pub fn get_fish(&mut self, image: &RgbImage) -> Option<Point> {
+    pub fn get_fish(&mut self, image: &RgbImage) -> Option<FishPosition> {
         self.rod
             .as_mut()
             .expect("Couldn't find rod")
             .get_fish(image, &self.outer)
-            .and_then(|p| match p {
-                FishPosition::Exact(p) => Some(p),
-                FishPosition::Approximative(p) => self.any_fish_hooked(image).then_some(p),
-            })
     }
 }
 
+#[derive(Clone, Copy)]
 pub enum Move {
     Left,
     Right,
@@ -378,3 +755,80 @@ impl Move {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    const SCREEN: Dimensions = Dimensions {
+        width: 400,
+        height: 200,
+    };
+
+    /// Screen with the mini-game bar between `x_min` and `x_max`
+    fn screen(bar: Option<(u32, u32)>) -> RgbImage {
+        RgbImage::from_fn(SCREEN.width, SCREEN.height, |x, y| match bar {
+            Some((x_min, x_max)) if (x_min..=x_max).contains(&x) && (160..176).contains(&y) => {
+                if (x_min + 40..x_min + 70).contains(&x) {
+                    Rgb([0xe8, 0xe8, 0xe8])
+                } else {
+                    Rgb([0x30, 0x30, 0x38])
+                }
+            }
+            _ => Rgb([0x8c, 0xa8, 0xb4]),
+        })
+    }
+
+    /// Mini-game restored from an area saved by a previous session
+    fn restored(name: &str, area: &str) -> MiniGame {
+        let path = env::temp_dir().join(name);
+        fs::write(&path, format!("{} {} {area}", SCREEN.width, SCREEN.height))
+            .expect("Couldn't write the cache");
+
+        let mut mini_game = MiniGame::new(Region {
+            point1: Point { x: 100, y: 150 },
+            point2: Point { x: 300, y: 190 },
+        });
+        assert!(mini_game.restore(&path, &SCREEN));
+        mini_game
+    }
+
+    #[test]
+    fn keeps_the_restored_area() {
+        let mut mini_game = restored("fischy_valid_mini_game.txt", "130 160 270 176");
+
+        assert!(mini_game.verify(&screen(Some((110, 290)))));
+        assert!(mini_game.refined);
+    }
+
+    #[test]
+    fn drops_a_stale_restored_area() {
+        // The bar moved since the area was saved
+        let mut mini_game = restored("fischy_stale_mini_game.txt", "30 20 170 36");
+
+        assert!(!mini_game.verify(&screen(Some((110, 290)))));
+        assert!(!mini_game.refined);
+        assert!(mini_game.refine_area(&screen(Some((110, 290)))).is_ok());
+    }
+
+    #[test]
+    fn rejects_degenerate_bands() {
+        let dark = RgbImage::from_pixel(SCREEN.width, SCREEN.height, Rgb([0x30, 0x30, 0x38]));
+
+        // Narrower than the frame borders, against the left edge of the screen
+        let narrow = Region {
+            point1: Point { x: 0, y: 150 },
+            point2: Point { x: 9, y: 190 },
+        };
+        assert!(MiniGame::search_structure(&dark, &narrow).is_none());
+
+        // Against the right edge of the screen
+        let edge = Region {
+            point1: Point { x: 300, y: 150 },
+            point2: Point { x: 399, y: 190 },
+        };
+        assert!(MiniGame::search_structure(&dark, &edge).is_none());
+    }
+}
@@ -2,7 +2,11 @@ use std::ops::Add;
 
 use image::{Rgb, RgbImage};
 
-use crate::utils::{colors::ColorTarget, fishing::MiniGame};
+use crate::utils::{
+    colors::{ColorTarget, Matcher},
+    fishing::MiniGame,
+    templates::Template,
+};
 
 #[derive(Clone)]
 pub struct Point {
@@ -41,15 +45,33 @@ impl Dimensions {
         let roblox_button_color = ColorTarget {
             color: Rgb([0xf7, 0xf7, 0xf8]),
             variation: 2,
+            matcher: Matcher::Rgb,
         };
 
         let x = self.width * 2 / 100;
 
-        let pos = (0..=(self.height / 2)).find_map(|y| {
-            roblox_button_color
-                .matches(img.get_pixel(x, y))
-                .then_some(Point { x, y: y + 10 })
-        });
+        let pos = (0..=(self.height / 2))
+            .find_map(|y| {
+                roblox_button_color
+                    .matches(img.get_pixel(x, y))
+                    .then_some(Point { x, y: y + 10 })
+            })
+            .or_else(|| {
+                // Fallback on the button appearance, when its color is off
+                Region {
+                    point1: Point { x: 0, y: 0 },
+                    point2: Point {
+                        x: self.width * 10 / 100,
+                        y: self.height / 2,
+                    },
+                }
+                .search_template(img, Template::RobloxButton, 80)
+                // Same anchor as the color search: just below the top of the button
+                .map(|m| Point {
+                    x,
+                    y: m.bounds.point1.y + 10,
+                })
+            });
 
         #[cfg(feature = "imageproc")]
         {
@@ -106,6 +128,21 @@ impl Dimensions {
         })
     }
 
+    /// Find where the cast power bar can appear
+    #[must_use]
+    pub fn calculate_power_bar_region(&self) -> Region {
+        Region {
+            point1: Point {
+                x: self.width * 40 / 100,
+                y: self.height * 20 / 100,
+            },
+            point2: Point {
+                x: self.width * 60 / 100,
+                y: self.height * 60 / 100,
+            },
+        }
+    }
+
     /// Find where shake bubble appears
     #[must_use]
     pub fn calculate_shake_region(&self, roblox_button_pos: Option<Point>) -> Region {
@@ -147,6 +184,24 @@ impl Region {
         }
     }
 
+    /// Returns `count` rows evenly spread around the middle half of the region
+    #[must_use]
+    pub fn scanlines(&self, count: u32) -> Vec<u32> {
+        let [_, y_min, _, y_max] = self.corners();
+        let (top, height) = (y_min + (y_max - y_min) / 4, (y_max - y_min) / 2);
+
+        (0..count)
+            .map(|i| top + height * (2 * i + 1) / (2 * count))
+            .collect()
+    }
+
+    /// Check if a point is inside the region
+    #[must_use]
+    pub fn contains(&self, point: &Point) -> bool {
+        let [x_min, y_min, x_max, y_max] = self.corners();
+        (x_min..=x_max).contains(&point.x) && (y_min..=y_max).contains(&point.y)
+    }
+
     /// Returns corners: `[x_min, y_min, x_max, y_max]`
     #[must_use]
     pub fn corners(&self) -> [u32; 4] {
@@ -0,0 +1,240 @@
+use image::{Rgb, RgbImage};
+
+use crate::utils::{
+    colors::ColorTarget,
+    geometry::{Point, Region},
+};
+
+/// Roblox hotbar, at the bottom of the screen: evenly spaced slots centered on the screen
+pub struct Hotbar {
+    /// Horizontal center of the hotbar
+    center: u32,
+    /// Distance between the centers of two slots
+    pitch: u32,
+    /// Number of slots
+    count: u16,
+    /// Width of a slot
+    width: u32,
+    /// Top of the slots
+    top: u32,
+    /// Bottom of the slots
+    bottom: u32,
+}
+
+/// Slots are dark and grey
+fn is_slot_pixel(pixel: Rgb<u8>) -> bool {
+    let Rgb([r, g, b]) = pixel;
+    let spread = r.max(g).max(b) - r.min(g).min(b);
+    ColorTarget::brightness(&pixel) < 90 && spread < 25
+}
+
+impl Hotbar {
+    /// Locate the hotbar slots
+    #[must_use]
+    pub fn search(screen: &RgbImage) -> Option<Self> {
+        let (width, height) = screen.dimensions();
+        let slot_size = (height * 3 / 100)..=(height * 8 / 100);
+        let max_gap = width * 15 / 1000;
+
+        // Keep the row with the most slots
+        let (y, runs) = (height * 85 / 100..height * 99 / 100)
+            .step_by(2)
+            .map(|y| {
+                let mut runs = Vec::new();
+                let mut start = None;
+                for x in 0..width {
+                    match (is_slot_pixel(*screen.get_pixel(x, y)), start) {
+                        (true, None) => start = Some(x),
+                        (false, Some(s)) => {
+                            if slot_size.contains(&(x - s)) {
+                                runs.push((s, x - 1));
+                            }
+                            start = None;
+                        }
+                        _ => (),
+                    }
+                }
+
+                (y, runs)
+            })
+            .filter(|(_, runs)| {
+                // Slots are next to each other, an item icon can hide one of them
+                let packed = runs
+                    .windows(2)
+                    .all(|w| w[1].0 - w[0].1 <= max_gap * 2 + slot_size.end());
+
+                // The hotbar is centered
+                let centered = runs
+                    .first()
+                    .zip(runs.last())
+                    .is_some_and(|((l, _), (_, r))| {
+                        l.midpoint(*r).abs_diff(width / 2) < width / 10
+                    });
+
+                packed && centered
+            })
+            .max_by_key(|(_, runs)| runs.len())?;
+
+        // Some slots can be missed, an item icon may break them
+        let centers = runs.iter().map(|(l, r)| l.midpoint(*r)).collect::<Vec<_>>();
+        let (first, last) = runs.first().zip(runs.last())?;
+        let slot_width = runs.iter().map(|(l, r)| r - l + 1).max()?;
+        let pitch = centers
+            .windows(2)
+            .map(|w| w[1] - w[0])
+            .min()
+            .unwrap_or(slot_width + max_gap / 2);
+
+        // From the center, slots are an odd number of half pitches away when their count is even
+        let center = width / 2;
+        let half_pitches = |x: u32| {
+            let offset = (x.abs_diff(center) * 2 + pitch / 2) / pitch;
+            u16::try_from(offset).unwrap_or(u16::MAX)
+        };
+        let farthest =
+            half_pitches(first.0.midpoint(first.1)).max(half_pitches(last.0.midpoint(last.1)));
+
+        let x = centers[0];
+        let top = (0..y)
+            .rev()
+            .take_while(|&y| is_slot_pixel(*screen.get_pixel(x, y)))
+            .last()
+            .unwrap_or(y);
+        let bottom = (y..height)
+            .take_while(|&y| is_slot_pixel(*screen.get_pixel(x, y)))
+            .last()
+            .unwrap_or(y);
+
+        let hotbar = Self {
+            center,
+            pitch,
+            count: farthest + 1,
+            width: slot_width,
+            top,
+            bottom,
+        };
+
+        #[cfg(feature = "imageproc")]
+        {
+            use crate::utils::debug::Drawable;
+            use std::sync::Arc;
+
+            (1..=hotbar.count)
+                .map(|number| hotbar.slot(number))
+                .collect::<Vec<_>>()
+                .draw_async(Arc::new(screen.clone()), "hotbar.png", true);
+        }
+
+        Some(hotbar)
+    }
+
+    /// Returns the area of a slot, starting at 1
+    fn slot(&self, number: u16) -> Region {
+        // Centers are spaced by a pitch, around the hotbar center
+        let half_pitches = i64::from(number) * 2 - 1 - i64::from(self.count);
+        let x = i64::from(self.center) + half_pitches * i64::from(self.pitch) / 2;
+        let x = u32::try_from(x).unwrap_or(0);
+
+        Region {
+            point1: Point {
+                x: x.saturating_sub(self.width / 2),
+                y: self.top,
+            },
+            point2: Point {
+                x: x + self.width / 2,
+                y: self.bottom,
+            },
+        }
+    }
+
+    /// Returns the slot under the position, starting at 1
+    #[cfg(test)]
+    fn slot_at(&self, x: u32) -> Option<u16> {
+        (1..=self.count).find(|&number| self.slot(number).contains(&Point { x, y: self.top }))
+    }
+
+    /// Returns the position of the highlighted slot, starting at 1
+    #[must_use]
+    pub fn selected(&self, screen: &RgbImage) -> Option<u16> {
+        let (width, height) = screen.dimensions();
+
+        // The selected slot has a bright border
+        let border_ratio = |slot: &Region| {
+            let [x_min, y_min, x_max, y_max] = slot.corners();
+            let (x_min, y_min) = (x_min.saturating_sub(2), y_min.saturating_sub(2));
+            let (x_max, y_max) = ((x_max + 2).min(width - 1), (y_max + 2).min(height - 1));
+
+            let border = (x_min..=x_max)
+                .flat_map(|x| [(x, y_min), (x, y_max)])
+                .chain((y_min..=y_max).flat_map(|y| [(x_min, y), (x_max, y)]))
+                .collect::<Vec<_>>();
+            let bright = border
+                .iter()
+                .filter(|(x, y)| ColorTarget::brightness(screen.get_pixel(*x, *y)) > 200)
+                .count();
+
+            bright * 100 / border.len()
+        };
+
+        (1..=self.count)
+            .map(|number| (number, border_ratio(&self.slot(number))))
+            .filter(|(_, ratio)| *ratio > 50)
+            .max_by_key(|(_, ratio)| *ratio)
+            .map(|(number, _)| number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLOT: u32 = 43;
+    const GAP: u32 = 6;
+
+    /// 720p screen with `count` slots, `icon` hiding the middle of a slot
+    fn screen(count: u32, selected: u32, icon: u32) -> RgbImage {
+        let left = 640 - (count * (SLOT + GAP) - GAP) / 2;
+        let (top, bottom) = (660, 660 + SLOT);
+
+        RgbImage::from_fn(1280, 720, |x, y| {
+            let slot = (x >= left - 2)
+                .then(|| (x + 2 - left) / (SLOT + GAP) + 1)
+                .filter(|slot| *slot <= count);
+            let Some(slot) = slot else {
+                return Rgb([0x80, 0x9a, 0x70]);
+            };
+            let l = left + (slot - 1) * (SLOT + GAP);
+            let inside = (l..l + SLOT).contains(&x) && (top..bottom).contains(&y);
+            let border = (l - 2..l + SLOT + 2).contains(&x) && (top - 2..bottom + 2).contains(&y);
+
+            if inside && slot == icon && (l + 10..l + SLOT - 10).contains(&x) {
+                Rgb([0xe0, 0xb0, 0x40])
+            } else if inside {
+                Rgb([0x30, 0x30, 0x30])
+            } else if border && slot == selected {
+                Rgb([0xff, 0xff, 0xff])
+            } else {
+                Rgb([0x80, 0x9a, 0x70])
+            }
+        })
+    }
+
+    #[test]
+    fn numbers_slots_by_position() {
+        // The icon of the second slot hides it
+        let hotbar = Hotbar::search(&screen(5, 0, 2)).expect("No hotbar");
+
+        assert_eq!(hotbar.count, 5);
+        assert_eq!(hotbar.selected(&screen(5, 3, 2)), Some(3));
+        assert_eq!(hotbar.slot_at(640 - SLOT - GAP), Some(2));
+    }
+
+    #[test]
+    fn numbers_an_even_hotbar() {
+        let hotbar = Hotbar::search(&screen(4, 0, 1)).expect("No hotbar");
+
+        assert_eq!(hotbar.count, 4);
+        assert_eq!(hotbar.selected(&screen(4, 4, 1)), Some(4));
+        assert_eq!(hotbar.slot_at(640 - (SLOT + GAP) * 3 / 2), Some(1));
+    }
+}
@@ -0,0 +1,45 @@
+use std::process::Command;
+
+/// Run the command selling the inventory, through the shell so it can be quoted
+///
+/// # Errors
+/// If the command couldn't be run or failed
+pub fn sell(command: &str) -> Result<(), String> {
+    if command.trim().is_empty() {
+        return Err("Empty sell command".to_string());
+    }
+
+    let (shell, flag) = if cfg!(target_os = "windows") {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let status = Command::new(shell)
+        .args([flag, command])
+        .status()
+        .map_err(|e| format!("Couldn't run the sell command: {e}"))?;
+    if !status.success() {
+        return Err(format!("Sell command failed ({status})"));
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_failures() {
+        assert_eq!(sell("true"), Ok(()));
+        assert!(sell("false").is_err());
+        assert!(sell("  ").is_err());
+        assert!(sell("fischy-no-such-command").is_err());
+    }
+
+    #[test]
+    fn keeps_quoted_arguments() {
+        assert_eq!(sell("test \"a  b\" = 'a  b'"), Ok(()));
+        assert!(sell("test a  b = 'a  b'").is_err());
+    }
+}
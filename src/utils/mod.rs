@@ -1,10 +1,23 @@
 pub mod args;
+pub mod calibration;
+pub mod cast;
+pub mod catch;
 pub mod checks;
 pub mod clickers;
 pub mod colors;
+pub mod cycle;
 pub mod fishing;
 pub mod geometry;
 pub mod helpers;
+pub mod hotbar;
+pub mod inventory;
+pub mod ocr;
+pub mod overlays;
+pub mod reconnect;
+pub mod scene;
+pub mod shake;
+pub mod templates;
+pub mod viewpoint;
 
 #[cfg(feature = "imageproc")]
 pub mod debug;
@@ -0,0 +1,488 @@
+use std::{collections::BTreeMap, fs, ops::Range, sync::LazyLock};
+
+use image::RgbImage;
+
+use crate::{
+    cache_path,
+    utils::{colors::ColorTarget, geometry::Region},
+};
+
+/// Rows of a glyph: 7 above the baseline and 2 for descenders
+const GLYPH_HEIGHT: usize = 9;
+
+/// Glyph rows a line of text can span, depending on whether it has
+/// capitals (or ascenders) and descenders
+const LINE_LAYOUTS: [Range<usize>; 4] = [0..9, 0..7, 2..9, 2..7];
+
+/// Columns of the widest glyph
+const MAX_GLYPH_WIDTH: usize = 8;
+
+/// Spans wider than this many columns may be two touching glyphs
+const SPLIT_WIDTH: usize = 5;
+
+/// How much better two glyphs have to match than a single one to split a span, in percent
+const MIN_SPLIT_GAIN: usize = 8;
+
+/// Smallest line height we can read, in pixels
+const MIN_LINE_HEIGHT: usize = 5;
+
+/// Rows sampled by the quick check for text, one every this many pixels
+const EDGE_ROW_STEP: usize = 3;
+
+/// Sharp brightness changes a sampled row needs to hold text
+const MIN_EDGES: usize = 6;
+
+/// Glyphs learned from the game, in the cache directory
+pub const LEARNED_GLYPHS: &str = "glyphs.txt";
+
+/// Glyph set of the UI font, bundled in the binary, the learned glyphs replacing it
+static GLYPHS: LazyLock<Vec<Glyph>> = LazyLock::new(|| {
+    let mut glyphs = Glyph::parse(include_str!("../../assets/glyphs.txt"));
+    if let Ok(learned) = fs::read_to_string(cache_path(LEARNED_GLYPHS)) {
+        for glyph in Glyph::parse(&learned) {
+            glyphs.retain(|g| g.character != glyph.character);
+            glyphs.push(glyph);
+        }
+    }
+
+    glyphs
+});
+
+/// Text read from the screen
+pub struct Text {
+    pub text: String,
+    /// Average glyph similarity, in percent
+    pub confidence: u8,
+}
+
+/// Line of text split into glyphs
+struct Line {
+    ink: Vec<Vec<bool>>,
+    /// Inked rows
+    ys: Range<usize>,
+    /// Columns of each glyph
+    spans: Vec<Range<usize>>,
+}
+
+impl Text {
+    /// Check if the text contains the word, ignoring the case and the characters
+    /// which can't be told apart
+    #[must_use]
+    pub fn contains(&self, word: &str) -> bool {
+        fold(&self.text).contains(&fold(word))
+    }
+}
+
+/// Lowercase the text, merging the characters which look the same at the glyph resolution,
+/// like the narrow strokes of `i`, `l` and `!`
+#[must_use]
+pub fn fold(text: &str) -> String {
+    text.chars()
+        .map(|c| match c.to_ascii_lowercase() {
+            'i' | '1' | '!' | '|' => 'l',
+            '0' => 'o',
+            '5' | '$' => 's',
+            c => c,
+        })
+        .collect()
+}
+
+/// Character of the UI font
+struct Glyph {
+    character: char,
+    /// Ink of each row in per-mille, without the empty columns around the character
+    rows: Vec<Vec<usize>>,
+}
+
+impl Glyph {
+    /// Parse a glyph set: a character, then its rows where `#` is ink, `.` is background,
+    /// and a digit is partial ink in tenths. Lines starting with `;` are comments
+    fn parse(source: &str) -> Vec<Self> {
+        let mut lines = source
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with(';'));
+
+        let mut glyphs = Vec::new();
+        while let Some(character) = lines.next().and_then(|line| line.chars().next()) {
+            let rows = lines
+                .by_ref()
+                .take(GLYPH_HEIGHT)
+                .map(|line| line.chars().map(Self::level).collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+
+            let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+            let inked = |x: &usize| rows.iter().any(|row| row.get(*x).is_some_and(|&l| l > 0));
+            let first = (0..width).find(inked).unwrap_or(0);
+            let last = (0..width).rfind(inked).unwrap_or(width.saturating_sub(1));
+
+            glyphs.push(Self {
+                character,
+                rows: rows
+                    .iter()
+                    .map(|row| {
+                        (first..=last)
+                            .map(|x| row.get(x).copied().unwrap_or(0))
+                            .collect()
+                    })
+                    .collect(),
+            });
+        }
+
+        glyphs
+    }
+
+    /// Ink of a glyph cell, in per-mille
+    fn level(cell: char) -> usize {
+        match cell {
+            '#' => 1000,
+            digit => digit.to_digit(10).map_or(0, |tenths| tenths as usize * 100),
+        }
+    }
+
+    /// Glyph cell of an ink level in per-mille, the opposite of [`Self::level`]
+    fn symbol(level: usize) -> char {
+        match (level + 50) / 100 {
+            0 => '.',
+            tenths @ 1..10 => u32::try_from(tenths)
+                .ok()
+                .and_then(|tenths| char::from_digit(tenths, 10))
+                .unwrap_or('#'),
+            _ => '#',
+        }
+    }
+
+    /// Tells if the glyph reaches the top row and the bottom row,
+    /// which only capitals (or ascenders) and descenders do
+    fn extent(&self) -> (bool, bool) {
+        let inked = |row: usize| self.rows[row].iter().any(|&level| level >= 500);
+        (inked(0), inked(GLYPH_HEIGHT - 1))
+    }
+
+    /// Check if the glyph can be part of a line spanning the `layout` rows
+    fn fits(&self, layout: &Range<usize>) -> bool {
+        let (tall, descends) = self.extent();
+        (!tall || layout.start == 0) && (!descends || layout.end == GLYPH_HEIGHT)
+    }
+
+    /// Compare the glyph with the ink of an area, the line spanning the `layout` rows
+    ///
+    /// # Return
+    /// Similarity, in percent
+    fn score(
+        &self,
+        ink: &[Vec<bool>],
+        xs: &Range<usize>,
+        ys: &Range<usize>,
+        layout: &Range<usize>,
+    ) -> usize {
+        let rows = &self.rows[layout.clone()];
+        let (width, height) = (rows[0].len(), rows.len());
+
+        // Each glyph cell is compared with the ink ratio of the matching area
+        let difference = rows
+            .iter()
+            .enumerate()
+            .flat_map(|(cy, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(move |(cx, &level)| (cx, cy, level))
+            })
+            .map(|(cx, cy, level)| {
+                ink_ratio(ink, &cell(xs, width, cx), &cell(ys, height, cy)).abs_diff(level)
+            })
+            .sum::<usize>();
+        let similarity = 1000 - difference / (width * height);
+
+        // Narrow glyphs shouldn't match wide shapes
+        let expected_width = (width * ys.len() / height).max(1);
+        let aspect = expected_width.min(xs.len()) * 1000 / expected_width.max(xs.len());
+
+        similarity * (500 + aspect / 2) / 10_000
+    }
+}
+
+/// Pixels of `range` covered by the cell `i` out of `count`, with how much of them is covered.
+/// Cells don't have to start on a pixel, so both sides of a glyph are sampled alike
+fn cell(range: &Range<usize>, count: usize, i: usize) -> Vec<(usize, usize)> {
+    // In 1/count of pixel
+    let (start, end) = (i * range.len(), (i + 1) * range.len());
+    (start / count..end.div_ceil(count))
+        .map(|p| {
+            let covered = end.min((p + 1) * count) - start.max(p * count);
+            (range.start + p, covered)
+        })
+        .collect()
+}
+
+/// Inked part of an area, in per-mille
+fn ink_ratio(ink: &[Vec<bool>], xs: &[(usize, usize)], ys: &[(usize, usize)]) -> usize {
+    let (mut inked, mut total) = (0, 0);
+    for &(y, y_weight) in ys {
+        for &(x, x_weight) in xs {
+            total += x_weight * y_weight;
+            if ink[y][x] {
+                inked += x_weight * y_weight;
+            }
+        }
+    }
+
+    inked * 1000 / total.max(1)
+}
+
+/// Split the text from the background with Otsu's threshold,
+/// the text being the least common class
+fn binarize(screen: &RgbImage, region: &Region) -> Vec<Vec<bool>> {
+    let [x_min, y_min, x_max, y_max] = region.corners();
+    let brightness = (y_min..=y_max)
+        .map(|y| {
+            (x_min..=x_max)
+                .map(|x| {
+                    ColorTarget::brightness(screen.get_pixel(x, y))
+                        .clamp(0, 255)
+                        .cast_unsigned() as usize
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut histogram = [0_i128; 256];
+    brightness.iter().flatten().for_each(|&b| histogram[b] += 1);
+    let total = histogram.iter().sum::<i128>();
+    let sum = histogram
+        .iter()
+        .zip(0..)
+        .map(|(count, b)| count * b)
+        .sum::<i128>();
+
+    // Maximize the variance between both classes
+    let (mut weight, mut weighted_sum) = (0, 0);
+    let mut best = (0, 0);
+    for ((b, count), value) in histogram.iter().enumerate().zip(0..) {
+        weight += count;
+        weighted_sum += count * value;
+        let other = total - weight;
+        if weight == 0 || other == 0 {
+            continue;
+        }
+
+        let spread = weighted_sum * other - (sum - weighted_sum) * weight;
+        let variance = spread * spread / (weight * other);
+        if variance > best.1 {
+            best = (b, variance);
+        }
+    }
+
+    let threshold = best.0;
+    let bright = brightness
+        .iter()
+        .flatten()
+        .filter(|&&b| b > threshold)
+        .count();
+    let text_is_bright = bright * 2 < brightness.len() * brightness[0].len();
+
+    brightness
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|&b| (b > threshold) == text_is_bright)
+                .collect()
+        })
+        .collect()
+}
+
+/// Quick check for sharp edges, before binarizing and matching glyphs
+fn has_edges(screen: &RgbImage, region: &Region) -> bool {
+    let [x_min, y_min, x_max, y_max] = region.corners();
+    (y_min..=y_max).step_by(EDGE_ROW_STEP).any(|y| {
+        (x_min..=x_max)
+            .map(|x| ColorTarget::brightness(screen.get_pixel(x, y)))
+            .collect::<Vec<_>>()
+            // Anti-aliased edges spread over two pixels
+            .windows(3)
+            .filter(|w| w[0].abs_diff(w[2]) > 80)
+            .count()
+            >= MIN_EDGES
+    })
+}
+
+/// Split the line of text of the region into glyphs
+fn segment(screen: &RgbImage, region: &Region) -> Option<Line> {
+    let ink = binarize(screen, region);
+    let inked_rows = (0..ink.len())
+        .filter(|&y| ink[y].contains(&true))
+        .collect::<Vec<_>>();
+    let ys = *inked_rows.first()?..*inked_rows.last()? + 1;
+    if ys.len() < MIN_LINE_HEIGHT {
+        return None;
+    }
+
+    // Glyphs are separated by empty columns
+    let mut spans = Vec::new();
+    let mut start = None;
+    for x in 0..=ink[0].len() {
+        let inked = x < ink[0].len() && ys.clone().any(|y| ink[y][x]);
+        match (inked, start) {
+            (true, None) => start = Some(x),
+            (false, Some(s)) => {
+                spans.push(s..x);
+                start = None;
+            }
+            _ => (),
+        }
+    }
+
+    Some(Line { ink, ys, spans })
+}
+
+/// Read a single line of text in the region
+#[must_use]
+pub fn read_text(screen: &RgbImage, region: &Region) -> Option<Text> {
+    #[cfg(feature = "imageproc")]
+    {
+        use crate::utils::debug::Drawable;
+        use std::sync::Arc;
+
+        region
+            .clone()
+            .draw_async(Arc::new(screen.clone()), "ocr.png", true);
+    }
+
+    if !has_edges(screen, region) {
+        return None;
+    }
+
+    let Line { ink, ys, spans } = segment(screen, region)?;
+    let space = ys.len() * 30 / 100;
+    LINE_LAYOUTS
+        .iter()
+        .map(|layout| {
+            let mut text = String::new();
+            let (mut total, mut count) = (0, 0);
+            for (i, xs) in spans.iter().enumerate() {
+                if i > 0 && xs.start - spans[i - 1].end > space {
+                    text.push(' ');
+                }
+
+                let single = best_glyph(&ink, xs, &ys, layout);
+                // Glyphs can touch, like the arm of `r` reaching the next one,
+                // the span is then wider than any glyph
+                let pair = (xs.len() * layout.len() > SPLIT_WIDTH * ys.len())
+                    .then(|| {
+                        (xs.start + 2..xs.end - 1)
+                            .map(|x| {
+                                let left = best_glyph(&ink, &(xs.start..x), &ys, layout);
+                                let right = best_glyph(&ink, &(x..xs.end), &ys, layout);
+                                ([left.0, right.0], left.1 + right.1)
+                            })
+                            .max_by_key(|(_, score)| *score)
+                    })
+                    .flatten()
+                    .filter(|(_, score)| score / 2 > single.1 + MIN_SPLIT_GAIN);
+
+                if let Some((characters, score)) = pair {
+                    text.extend(characters);
+                    (total, count) = (total + score, count + 2);
+                } else {
+                    text.push(single.0);
+                    (total, count) = (total + single.1, count + 1);
+                }
+            }
+
+            Text {
+                text,
+                confidence: u8::try_from(total / count.max(1)).unwrap_or(100),
+            }
+        })
+        .max_by_key(|text| text.confidence)
+}
+
+/// Most similar glyph to the ink of an area, with its score
+fn best_glyph(
+    ink: &[Vec<bool>],
+    xs: &Range<usize>,
+    ys: &Range<usize>,
+    layout: &Range<usize>,
+) -> (char, usize) {
+    GLYPHS
+        .iter()
+        .filter(|glyph| glyph.fits(layout))
+        .map(|glyph| (glyph.character, glyph.score(ink, xs, ys, layout)))
+        .max_by_key(|(_, score)| *score)
+        .unwrap_or(('?', 0))
+}
+
+/// Learn the glyphs of a line of text of the game, knowing what it says.
+/// Which rows the line spans is told by the known glyphs of its characters
+///
+/// # Errors
+/// If the line couldn't be split into one glyph per character
+///
+/// # Return
+/// Glyphs of each character of the text, in the glyph set format
+pub fn learn_glyphs(screen: &RgbImage, region: &Region, text: &str) -> Result<String, String> {
+    let Line { ink, ys, spans } = segment(screen, region).ok_or("No text found")?;
+    let characters = text
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<Vec<_>>();
+    if spans.len() != characters.len() {
+        return Err(format!(
+            "Found {} glyphs for the {} characters of {text:?}",
+            spans.len(),
+            characters.len()
+        ));
+    }
+
+    let (tall, descends) = characters
+        .iter()
+        .filter_map(|c| GLYPHS.iter().find(|glyph| glyph.character == *c))
+        .map(Glyph::extent)
+        .fold((false, false), |(t, d), (gt, gd)| (t || gt, d || gd));
+    let layout = &LINE_LAYOUTS[match (tall, descends) {
+        (true, true) => 0,
+        (true, false) => 1,
+        (false, true) => 2,
+        (false, false) => 3,
+    }];
+
+    // Ink ratio of each cell, summed over the occurrences of the same width
+    let mut samples = BTreeMap::<char, (usize, Vec<Vec<usize>>)>::new();
+    for (character, xs) in characters.into_iter().zip(spans) {
+        let width = ((xs.len() * layout.len() + ys.len() / 2) / ys.len()).clamp(1, MAX_GLYPH_WIDTH);
+        let (count, cells) = samples
+            .entry(character)
+            .or_insert_with(|| (0, vec![vec![0; width]; layout.len()]));
+        if cells[0].len() != width {
+            continue;
+        }
+
+        *count += 1;
+        for (cy, row) in cells.iter_mut().enumerate() {
+            for (cx, sum) in row.iter_mut().enumerate() {
+                *sum += ink_ratio(&ink, &cell(&xs, width, cx), &cell(&ys, layout.len(), cy));
+            }
+        }
+    }
+
+    Ok(samples
+        .into_iter()
+        .map(|(character, (count, cells))| {
+            let rows = (0..GLYPH_HEIGHT)
+                .map(|y| {
+                    (0..cells[0].len())
+                        .map(|x| {
+                            let level = if layout.contains(&y) {
+                                cells[y - layout.start][x] / count
+                            } else {
+                                0
+                            };
+                            Glyph::symbol(level)
+                        })
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>();
+            format!("\n{character}\n{}\n", rows.join("\n"))
+        })
+        .collect::<Vec<_>>()
+        .concat())
+}
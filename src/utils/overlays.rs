@@ -0,0 +1,455 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use enigo::{Button, Direction::Click, Enigo, Key, Keyboard, Mouse};
+use image::RgbImage;
+use log::info;
+
+use crate::{
+    Scroller, sleep,
+    utils::{
+        colors::ColorTarget,
+        geometry::{Point, Region},
+        ocr::read_text,
+    },
+};
+
+/// Minimum OCR confidence for the words rule
+const MIN_TEXT_CONFIDENCE: u8 = 60;
+
+/// Step between two pixels checked by the color rule
+const COLOR_SAMPLING: usize = 2;
+
+/// What the overlay position is relative to
+pub enum Anchor {
+    /// Top left corner of the screen
+    Screen,
+    /// Roblox button, at the top left of the game
+    RobloxButton,
+}
+
+/// How to tell that an overlay is visible
+pub enum Rule {
+    /// Percentage of the area matching a color
+    Color {
+        target: ColorTarget,
+        percentage: usize,
+    },
+    /// Brightness transitions counted along columns, typical of text
+    Text {
+        /// Columns, in per-mille of the screen width relative to the anchor
+        columns: &'static [i32],
+        transitions: usize,
+    },
+    /// Right-most run of pixels matching a color, searched on the top then the bottom row
+    Mark {
+        target: ColorTarget,
+        /// Length of the run, in per-mille of the screen width
+        length: u32,
+    },
+    /// Text containing one of the words, in lowercase
+    Words(&'static [&'static str]),
+}
+
+/// How to dismiss an overlay
+pub enum Action {
+    /// Press a key
+    Key(Key),
+    /// Click at a position, in per-mille of the screen relative to the anchor
+    Click([i32; 2]),
+    /// Click where the rule matched
+    ClickMatch,
+    /// Stop the macro
+    Shutdown,
+    /// Nothing to do here, the caller reacts to it
+    Report,
+}
+
+/// Something covering the game that we have to get rid of
+pub struct Overlay {
+    pub name: &'static str,
+    pub anchor: Anchor,
+    /// Area `[x1, y1, x2, y2]`, in per-mille of the screen relative to the anchor
+    pub area: [i32; 4],
+    /// Whole area covered by the overlay when larger than `area`, in the same unit
+    pub extent: Option<[i32; 4]>,
+    pub rule: Rule,
+    pub action: Action,
+}
+
+/// Convert a per-mille position relative to `origin` to an absolute one
+fn locate(img: &RgbImage, origin: &Point, [x, y]: [i32; 2]) -> Point {
+    let (width, height) = img.dimensions();
+    let offset = |per_mille: i32, size: u32| per_mille * size.cast_signed() / 1000;
+
+    Point {
+        x: (origin.x.cast_signed() + offset(x, width))
+            .clamp(0, width.cast_signed() - 1)
+            .cast_unsigned(),
+        y: (origin.y.cast_signed() + offset(y, height))
+            .clamp(0, height.cast_signed() - 1)
+            .cast_unsigned(),
+    }
+}
+
+/// Count brightness transitions along the columns `xs`
+#[must_use]
+pub fn text_detection(xs: &[u32], y_min: u32, y_max: u32, img: &RgbImage) -> usize {
+    xs.iter()
+        .map(|&x| {
+            (y_min..y_max)
+                .map(|y| ColorTarget::brightness(img.get_pixel(x, y)))
+                .collect::<Vec<_>>()
+                .windows(2)
+                .filter(|w| w[0].abs_diff(w[1]) > 100)
+                .count()
+        })
+        .sum::<usize>()
+}
+
+impl Overlay {
+    /// Returns the origin of the overlay, if its anchor is known
+    fn origin(&self, roblox_anchor: Option<&Point>) -> Option<Point> {
+        match self.anchor {
+            Anchor::Screen => Some(Point { x: 0, y: 0 }),
+            Anchor::RobloxButton => roblox_anchor.cloned(),
+        }
+    }
+
+    /// Absolute area of the overlay
+    fn region(&self, img: &RgbImage, origin: &Point) -> Region {
+        Self::absolute(img, origin, self.area)
+    }
+
+    /// Absolute area covered by the overlay
+    fn cover(&self, img: &RgbImage, origin: &Point) -> Region {
+        Self::absolute(img, origin, self.extent.unwrap_or(self.area))
+    }
+
+    fn absolute(img: &RgbImage, origin: &Point, [x1, y1, x2, y2]: [i32; 4]) -> Region {
+        Region {
+            point1: locate(img, origin, [x1, y1]),
+            point2: locate(img, origin, [x2, y2]),
+        }
+    }
+
+    /// Check if the overlay is visible
+    ///
+    /// # Return
+    /// Where the overlay has been found
+    #[must_use]
+    pub fn detect(&self, img: &RgbImage, roblox_anchor: Option<&Point>) -> Option<Point> {
+        let origin = self.origin(roblox_anchor)?;
+        let area = self.region(img, &origin);
+        let [x_min, y_min, x_max, y_max] = area.corners();
+
+        #[cfg(feature = "imageproc")]
+        {
+            use crate::utils::debug::Drawable;
+            use std::sync::Arc;
+
+            area.clone().draw_async(
+                Arc::new(img.clone()),
+                format!("overlays/{}.png", self.name),
+                true,
+            );
+        }
+
+        let center = Point {
+            x: x_min.midpoint(x_max),
+            y: y_min.midpoint(y_max),
+        };
+
+        match &self.rule {
+            Rule::Color { target, percentage } => {
+                let target = target.prepare();
+                let (matching_pixels, area) = (x_min..x_max)
+                    .step_by(COLOR_SAMPLING)
+                    .flat_map(|x| (y_min..y_max).step_by(COLOR_SAMPLING).map(move |y| (x, y)))
+                    .fold((0, 0), |(matching, total), (x, y)| {
+                        (
+                            matching + usize::from(target.matches(img.get_pixel(x, y))),
+                            total + 1,
+                        )
+                    });
+
+                // The area can be empty on tiny screens
+                (matching_pixels * 100)
+                    .checked_div(area)
+                    .is_some_and(|matching| matching > *percentage)
+                    .then_some(center)
+            }
+            Rule::Text {
+                columns,
+                transitions,
+            } => {
+                let xs = columns
+                    .iter()
+                    .map(|&x| locate(img, &origin, [x, 0]).x)
+                    .collect::<Vec<_>>();
+
+                (text_detection(&xs, y_min, y_max, img) >= *transitions).then_some(center)
+            }
+            Rule::Mark { target, length } => {
+                let target = target.prepare();
+                let length = (img.width() * length / 1000).max(1);
+                [y_min, y_max].iter().find_map(|&y| {
+                    (x_min..x_max)
+                        .rev()
+                        .find(|&x| {
+                            (x..(x + length).min(img.width()))
+                                .filter(|&px| target.matches(img.get_pixel(px, y)))
+                                .count()
+                                >= length as usize
+                        })
+                        // Always click on the top row
+                        .map(|x| Point { x, y: y_min })
+                })
+            }
+            Rule::Words(words) => read_text(img, &area)
+                .filter(|text| text.confidence >= MIN_TEXT_CONFIDENCE)
+                .is_some_and(|text| words.iter().any(|word| text.contains(word)))
+                .then_some(center),
+        }
+    }
+
+    /// Get rid of the overlay
+    ///
+    /// # Panics
+    /// If couldn't use the mouse or the keyboard
+    fn dismiss(
+        &self,
+        enigo: &mut Enigo,
+        img: &RgbImage,
+        origin: &Point,
+        found: &Point,
+        cond: &AtomicBool,
+    ) {
+        let click = |enigo: &mut Enigo, p: &Point| {
+            enigo
+                .move_mouse_ig_abs(p.x.cast_signed(), p.y.cast_signed())
+                .expect("Couldn't move mouse to the overlay");
+            sleep(Duration::from_millis(100), cond); // we may move the mouse too fast
+            enigo
+                .button(Button::Left, Click)
+                .expect("Couldn't click on the overlay");
+        };
+
+        match self.action {
+            Action::Key(key) => enigo
+                .key(key, Click)
+                .expect("Couldn't press key to dismiss the overlay"),
+            Action::Click(position) => {
+                let p = locate(img, origin, position);
+
+                #[cfg(feature = "imageproc")]
+                {
+                    use crate::utils::debug::Drawable;
+                    use std::sync::Arc;
+
+                    p.clone().draw_async(
+                        Arc::new(img.clone()),
+                        format!("overlays/{}_button.png", self.name),
+                        true,
+                    );
+                }
+
+                click(enigo, &p);
+            }
+            Action::ClickMatch => click(enigo, found),
+            Action::Shutdown => {
+                info!("Closing due to {}...", self.name);
+                cond.store(true, Ordering::Relaxed);
+            }
+            Action::Report => (),
+        }
+    }
+}
+
+/// Overlay visible on the screen
+pub struct Sighting<'a> {
+    pub overlay: &'a Overlay,
+    /// Absolute area covered by the overlay
+    pub area: Region,
+    origin: Point,
+    found: Point,
+}
+
+impl Sighting<'_> {
+    /// Get rid of the overlay
+    pub fn dismiss(&self, enigo: &mut Enigo, img: &RgbImage, cond: &AtomicBool) {
+        info!("Dismissing {}", self.overlay.name);
+        self.overlay
+            .dismiss(enigo, img, &self.origin, &self.found, cond);
+    }
+}
+
+/// Returns the visible overlays, in order
+#[must_use]
+pub fn find_overlays<'a>(
+    img: &RgbImage,
+    roblox_anchor: Option<&Point>,
+    overlays: &'a [Overlay],
+) -> Vec<Sighting<'a>> {
+    overlays
+        .iter()
+        .filter_map(|overlay| {
+            let origin = overlay.origin(roblox_anchor)?;
+            let found = overlay.detect(img, roblox_anchor)?;
+            Some(Sighting {
+                overlay,
+                area: overlay.cover(img, &origin),
+                origin,
+                found,
+            })
+        })
+        .collect()
+}
+
+/// Dismiss every visible overlay, in order
+///
+/// # Return
+/// Names of the dismissed overlays
+pub fn dismiss_overlays(
+    enigo: &mut Enigo,
+    img: &RgbImage,
+    roblox_anchor: Option<&Point>,
+    overlays: &[Overlay],
+    cond: &AtomicBool,
+) -> Vec<&'static str> {
+    find_overlays(img, roblox_anchor, overlays)
+        .iter()
+        .map(|sighting| {
+            sighting.dismiss(enigo, img, cond);
+            sighting.overlay.name
+        })
+        .collect()
+}
+
+/// Keeps track of the overlays staying on the screen, to count each appearance once
+#[derive(Default)]
+pub struct Appearances {
+    visible: Vec<&'static str>,
+    new: Vec<&'static str>,
+}
+
+impl Appearances {
+    /// Update the visible overlays
+    pub fn update(&mut self, visible: Vec<&'static str>) {
+        self.new.extend(
+            visible
+                .iter()
+                .filter(|name| !self.visible.contains(name))
+                .copied(),
+        );
+        self.visible = visible;
+    }
+
+    /// Returns the overlays which appeared since the last call
+    pub fn take(&mut self) -> Vec<&'static str> {
+        std::mem::take(&mut self.new)
+    }
+}
+
+/// Searches overlays once every few frames, reading their text is too slow for each frame
+pub struct OverlayWatch {
+    overlays: &'static [Overlay],
+    /// Frames between two searches
+    period: u8,
+    /// Frames left before the next search
+    countdown: u8,
+    /// Areas covered by the overlays of the last search
+    areas: Vec<Region>,
+    appearances: Appearances,
+}
+
+impl OverlayWatch {
+    #[must_use]
+    pub fn new(overlays: &'static [Overlay], period: u8) -> Self {
+        Self {
+            overlays,
+            period,
+            countdown: 0,
+            areas: Vec::new(),
+            appearances: Appearances::default(),
+        }
+    }
+
+    /// Search the overlays when it's time to
+    ///
+    /// # Return
+    /// Visible overlays, always empty between two searches
+    pub fn check(&mut self, img: &RgbImage) -> Vec<Sighting<'static>> {
+        if self.countdown > 0 {
+            self.countdown -= 1;
+            return Vec::new();
+        }
+        self.countdown = self.period.saturating_sub(1);
+
+        let sightings = find_overlays(img, None, self.overlays);
+        self.areas = sightings
+            .iter()
+            .map(|sighting| sighting.area.clone())
+            .collect();
+        self.appearances.update(
+            sightings
+                .iter()
+                .map(|sighting| sighting.overlay.name)
+                .collect(),
+        );
+
+        sightings
+    }
+
+    /// Areas covered by the overlays of the last search
+    #[must_use]
+    pub fn areas(&self) -> &[Region] {
+        &self.areas
+    }
+
+    /// Returns the overlays which appeared since the last call
+    pub fn take(&mut self) -> Vec<&'static str> {
+        self.appearances.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::colors::Matcher;
+    use image::Rgb;
+
+    const DARK: Overlay = Overlay {
+        name: "dark",
+        anchor: Anchor::Screen,
+        area: [0, 0, 1000, 1000],
+        extent: None,
+        rule: Rule::Color {
+            target: ColorTarget {
+                color: Rgb([0, 0, 0]),
+                variation: 0,
+                matcher: Matcher::Rgb,
+            },
+            percentage: 50,
+        },
+        action: Action::Report,
+    };
+
+    #[test]
+    fn searches_every_few_frames() {
+        let img = RgbImage::new(100, 100);
+        let mut watch = OverlayWatch::new(&[DARK], 3);
+
+        assert_eq!(watch.check(&img).len(), 1);
+        assert!(watch.check(&img).is_empty());
+        assert!(watch.check(&img).is_empty());
+        // The overlay is still avoided in between
+        assert_eq!(watch.areas().len(), 1);
+        assert_eq!(watch.check(&img).len(), 1);
+
+        // Seen once, while it stayed on the screen
+        assert_eq!(watch.take(), vec!["dark"]);
+    }
+}
@@ -0,0 +1,150 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use image::RgbImage;
+
+use crate::{
+    sleep,
+    utils::{
+        geometry::{Point, Region},
+        templates::{Template, TemplateMatch},
+    },
+};
+
+/// Minimum score for a button to be recognized
+const MIN_BUTTON_SCORE: u8 = 70;
+
+/// Buttons of the popup shown when the server have shutdown
+pub struct DisconnectPopup {
+    pub reconnect: TemplateMatch,
+    pub leave: Option<TemplateMatch>,
+}
+
+impl DisconnectPopup {
+    /// Locate the popup buttons, in the middle of the screen
+    #[must_use]
+    pub fn search(screen: &RgbImage) -> Option<Self> {
+        let (width, height) = screen.dimensions();
+        let region = Region {
+            point1: Point {
+                x: width * 25 / 100,
+                y: height * 40 / 100,
+            },
+            point2: Point {
+                x: width * 75 / 100,
+                y: height * 75 / 100,
+            },
+        };
+
+        let reconnect = region.search_template(screen, Template::Reconnect, MIN_BUTTON_SCORE)?;
+        let leave = region.search_template(screen, Template::Leave, MIN_BUTTON_SCORE);
+
+        Some(Self { reconnect, leave })
+    }
+}
+
+/// Delays between retries, doubling each time up to a maximum
+pub struct Backoff {
+    delay: Duration,
+    max_delay: Duration,
+    tries: u8,
+    max_tries: u8,
+}
+
+impl Backoff {
+    #[must_use]
+    pub fn new(delay: Duration, max_delay: Duration, max_tries: u8) -> Self {
+        Self {
+            delay,
+            max_delay,
+            tries: 0,
+            max_tries,
+        }
+    }
+
+    /// Returns the delay to wait before the next try, if any try is left
+    pub fn next_delay(&mut self) -> Option<Duration> {
+        (self.tries < self.max_tries).then(|| {
+            let delay = 2_u32
+                .checked_pow(u32::from(self.tries))
+                .and_then(|factor| self.delay.checked_mul(factor))
+                .map_or(self.max_delay, |delay| delay.min(self.max_delay));
+            self.tries += 1;
+            delay
+        })
+    }
+
+    /// Number of tries done
+    #[must_use]
+    pub fn tries(&self) -> u8 {
+        self.tries
+    }
+}
+
+/// Run `attempt` until it succeeds, waiting longer between each try
+///
+/// # Return
+/// If an attempt succeeded
+pub fn retry(backoff: &mut Backoff, mut attempt: impl FnMut() -> bool, cond: &AtomicBool) -> bool {
+    while let Some(delay) = backoff.next_delay() {
+        if cond.load(Ordering::Relaxed) {
+            return false;
+        }
+        if attempt() {
+            return true;
+        }
+        sleep(delay, cond);
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_until_success() {
+        let mut backoff = Backoff::new(Duration::from_millis(1), Duration::from_millis(10), 5);
+        let mut attempts = 0;
+
+        assert!(retry(
+            &mut backoff,
+            || {
+                attempts += 1;
+                attempts == 3
+            },
+            &AtomicBool::new(false)
+        ));
+        assert_eq!(backoff.tries(), 3);
+    }
+
+    #[test]
+    fn gives_up_after_the_last_try() {
+        let mut backoff = Backoff::new(Duration::from_millis(1), Duration::from_millis(10), 3);
+        let mut attempts = 0;
+
+        assert!(!retry(
+            &mut backoff,
+            || {
+                attempts += 1;
+                false
+            },
+            &AtomicBool::new(false)
+        ));
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn caps_the_delay() {
+        let mut backoff = Backoff::new(Duration::from_secs(5), Duration::from_secs(50), u8::MAX);
+        let delays = std::iter::from_fn(|| backoff.next_delay()).collect::<Vec<_>>();
+
+        assert_eq!(delays.len(), usize::from(u8::MAX));
+        assert_eq!(delays[..5], [5, 10, 20, 40, 50].map(Duration::from_secs));
+        // Doubling overflows long before the last tries
+        assert!(delays.iter().all(|delay| *delay <= Duration::from_secs(50)));
+    }
+}
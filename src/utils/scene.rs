@@ -0,0 +1,296 @@
+use image::{
+    GrayImage, RgbImage,
+    imageops::{self, FilterType},
+};
+
+use crate::utils::{helpers::BadCast, templates::ncc};
+
+/// Width of the thumbnails being compared, small enough to ignore waves and particles
+const THUMBNAIL_WIDTH: u32 = 48;
+
+/// Similarity under which the character isn't where it was fishing anymore, in percent
+const MIN_SIMILARITY: u8 = 40;
+
+/// Brightness spread under which the frame is a loading screen
+const MAX_LOADING_SPREAD: u8 = 12;
+
+/// Largest view shift searched, in thumbnail pixels
+const MAX_SHIFT: i32 = 6;
+
+/// Scene changes in a row before giving up
+const MAX_SCENE_CHANGES: u8 = 3;
+
+/// View shift from which we walk back, in thumbnail pixels
+const DRIFT_THRESHOLD: i32 = 2;
+
+/// What happened to the character
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SceneChange {
+    /// Respawn or teleport in progress
+    Loading,
+    /// The view is completely different
+    Moved,
+}
+
+/// View of the scene while fishing
+pub struct Scene {
+    reference: GrayImage,
+}
+
+/// Grayscale and tiny version of the screen
+fn thumbnail(screen: &RgbImage) -> GrayImage {
+    let (width, height) = screen.dimensions();
+    imageops::resize(
+        &imageops::grayscale(screen),
+        THUMBNAIL_WIDTH,
+        (height * THUMBNAIL_WIDTH / width).max(1),
+        FilterType::Triangle,
+    )
+}
+
+impl Scene {
+    /// Take the reference view, once the camera is set up
+    #[must_use]
+    pub fn capture(screen: &RgbImage) -> Self {
+        Self {
+            reference: thumbnail(screen),
+        }
+    }
+
+    /// Returns how close the screen is to the reference, in percent
+    #[must_use]
+    pub fn similarity(&self, screen: &RgbImage) -> u8 {
+        self.compare(&thumbnail(screen))
+    }
+
+    fn compare(&self, current: &GrayImage) -> u8 {
+        if current.dimensions() != self.reference.dimensions() {
+            return 0;
+        }
+
+        u8::try_from((ncc(current, &self.reference, (0, 0)) * 100.).bad_cast()).unwrap_or(0)
+    }
+
+    /// Returns how much the view moved since the reference, in thumbnail pixels.
+    /// Something at `(x, y)` in the reference is now at `(x + dx, y + dy)`
+    #[must_use]
+    pub fn shift(&self, screen: &RgbImage) -> Option<(i32, i32)> {
+        let current = thumbnail(screen);
+        if current.dimensions() != self.reference.dimensions() {
+            return None;
+        }
+
+        let (width, height) = current.dimensions();
+        (-MAX_SHIFT..=MAX_SHIFT)
+            .flat_map(|dy| (-MAX_SHIFT..=MAX_SHIFT).map(move |dx| (dx, dy)))
+            .map(|(dx, dy)| {
+                // Only compare the part visible in both views
+                let (w, h) = (width - dx.unsigned_abs(), height - dy.unsigned_abs());
+                let before = imageops::crop_imm(
+                    &self.reference,
+                    (-dx).max(0).cast_unsigned(),
+                    (-dy).max(0).cast_unsigned(),
+                    w,
+                    h,
+                )
+                .to_image();
+                let after = imageops::crop_imm(
+                    &current,
+                    dx.max(0).cast_unsigned(),
+                    dy.max(0).cast_unsigned(),
+                    w,
+                    h,
+                )
+                .to_image();
+
+                ((dx, dy), ncc(&after, &before, (0, 0)))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            // Not the same place at all
+            .filter(|(_, score)| *score > 0.6)
+            .map(|(shift, _)| shift)
+    }
+
+    /// Compare the screen with the reference
+    #[must_use]
+    pub fn check(&self, screen: &RgbImage) -> Option<SceneChange> {
+        let current = thumbnail(screen);
+        let (min, max) = current.pixels().fold((u8::MAX, u8::MIN), |(min, max), p| {
+            (min.min(p[0]), max.max(p[0]))
+        });
+
+        // Loading screens are plain
+        if max - min <= MAX_LOADING_SPREAD {
+            return Some(SceneChange::Loading);
+        }
+
+        (self.compare(&current) < MIN_SIMILARITY).then_some(SceneChange::Moved)
+    }
+}
+
+/// Follow the scene during the session
+pub struct SceneWatch {
+    /// View of the fishing spot
+    pub scene: Scene,
+    /// Scene changes in a row
+    changes: u8,
+}
+
+impl SceneWatch {
+    #[must_use]
+    pub fn new(scene: Scene) -> Self {
+        Self { scene, changes: 0 }
+    }
+
+    /// Compare the screen with the fishing spot
+    ///
+    /// # Errors
+    /// If the scene keeps changing, the character isn't at the fishing spot anymore
+    pub fn check(&mut self, screen: &RgbImage) -> Result<Option<SceneChange>, String> {
+        let Some(change) = self.scene.check(screen) else {
+            self.changes = 0;
+            return Ok(None);
+        };
+
+        self.changes += 1;
+        if self.changes > MAX_SCENE_CHANGES {
+            return Err(format!("The scene changed {} times in a row", self.changes));
+        }
+
+        Ok(Some(change))
+    }
+}
+
+/// Direction to walk, from the character point of view
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Walk {
+    Forward,
+    Backward,
+    Left,
+    Right,
+}
+
+/// Walk back to the fishing spot when the view drifts
+pub struct DriftWatch {
+    /// Steps walked back to the fishing spot since the view left it
+    corrections: u16,
+    /// Steps allowed per drift, so we don't walk off a dock
+    max_corrections: u16,
+}
+
+impl DriftWatch {
+    #[must_use]
+    pub fn new(max_corrections: u16) -> Self {
+        Self {
+            corrections: 0,
+            max_corrections,
+        }
+    }
+
+    /// Decide where to walk from the view shift
+    ///
+    /// # Return
+    /// Directions to walk, empty when the view is on target
+    ///
+    /// # Errors
+    /// If too many steps were needed
+    pub fn correct(&mut self, shift: Option<(i32, i32)>) -> Result<Vec<Walk>, String> {
+        let Some((dx, dy)) = shift else {
+            return Ok(Vec::new());
+        };
+        if dx.abs() < DRIFT_THRESHOLD && dy.abs() < DRIFT_THRESHOLD {
+            // Back on target, the next drift gets its own steps
+            self.corrections = 0;
+            return Ok(Vec::new());
+        }
+
+        if self.corrections >= self.max_corrections {
+            return Err(format!(
+                "Drifted by ({dx}, {dy}) after {} steps",
+                self.corrections
+            ));
+        }
+        self.corrections += 1;
+
+        // The view moves the opposite way of the character
+        Ok([
+            (dx >= DRIFT_THRESHOLD, Walk::Right),
+            (dx <= -DRIFT_THRESHOLD, Walk::Left),
+            (dy >= DRIFT_THRESHOLD, Walk::Backward),
+            (dy <= -DRIFT_THRESHOLD, Walk::Forward),
+        ]
+        .into_iter()
+        .filter_map(|(needed, walk)| needed.then_some(walk))
+        .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgb;
+
+    use super::*;
+
+    /// Checkerboard with tiles of `size` pixels
+    fn spot(size: u32) -> RgbImage {
+        RgbImage::from_fn(480, 270, |x, y| {
+            if (x / size + y / size).is_multiple_of(2) {
+                Rgb([0x2b, 0x6c, 0x9e])
+            } else {
+                Rgb([0xc8, 0xb4, 0x8c])
+            }
+        })
+    }
+
+    #[test]
+    fn gives_up_when_the_scene_keeps_changing() {
+        let mut watch = SceneWatch::new(Scene::capture(&spot(60)));
+        let loading = RgbImage::from_pixel(480, 270, Rgb([0x10, 0x10, 0x10]));
+
+        assert_eq!(watch.check(&spot(60)), Ok(None));
+        for _ in 0..MAX_SCENE_CHANGES {
+            assert_eq!(watch.check(&loading), Ok(Some(SceneChange::Loading)));
+        }
+        assert!(watch.check(&spot(25)).is_err());
+    }
+
+    #[test]
+    fn scene_changes_have_to_be_in_a_row() {
+        let mut watch = SceneWatch::new(Scene::capture(&spot(60)));
+
+        for _ in 0..=MAX_SCENE_CHANGES {
+            assert_eq!(watch.check(&spot(25)), Ok(Some(SceneChange::Moved)));
+            assert_eq!(watch.check(&spot(60)), Ok(None));
+        }
+    }
+
+    #[test]
+    fn walks_against_the_drift() {
+        let mut watch = DriftWatch::new(2);
+
+        assert_eq!(watch.correct(None), Ok(Vec::new()));
+        assert_eq!(watch.correct(Some((1, -1))), Ok(Vec::new()));
+        assert_eq!(
+            watch.correct(Some((3, -2))),
+            Ok(vec![Walk::Right, Walk::Forward])
+        );
+        assert_eq!(watch.correct(Some((-4, 0))), Ok(vec![Walk::Left]));
+        assert!(watch.correct(Some((0, 5))).is_err());
+    }
+
+    #[test]
+    fn caps_each_drift() {
+        let mut watch = DriftWatch::new(2);
+
+        for _ in 0..5 {
+            assert_eq!(watch.correct(Some((4, 0))), Ok(vec![Walk::Right]));
+            assert_eq!(watch.correct(Some((0, -4))), Ok(vec![Walk::Forward]));
+            assert_eq!(watch.correct(Some((0, 0))), Ok(Vec::new()));
+        }
+        // Missing frames don't end the drift
+        assert_eq!(watch.correct(Some((4, 0))), Ok(vec![Walk::Right]));
+        assert_eq!(watch.correct(None), Ok(Vec::new()));
+        assert_eq!(watch.correct(Some((4, 0))), Ok(vec![Walk::Right]));
+        assert!(watch.correct(Some((4, 0))).is_err());
+    }
+}
@@ -0,0 +1,265 @@
+use std::time::{Duration, Instant};
+
+use image::{Rgb, RgbImage};
+
+use crate::utils::{
+    colors::{ColorTarget, Matcher, PreparedTarget},
+    geometry::{Point, Region},
+};
+
+/// Minimum score for a candidate to be considered a shake bubble
+pub const MIN_BUBBLE_SCORE: u8 = 60;
+
+/// Shake bubble candidate
+#[derive(Clone)]
+pub struct Bubble {
+    /// Center of the bubble
+    pub center: Point,
+    /// Bounding box of the bubble
+    pub bounds: Region,
+    /// Percentage of how much it looks like a shake bubble
+    pub score: u8,
+}
+
+/// Group of connected white pixels
+struct Blob {
+    pixels: u32,
+    sum_x: u64,
+    sum_y: u64,
+    bounds: [u32; 4],
+}
+
+impl Blob {
+    /// Rate how much the blob looks like a shake bubble
+    ///
+    /// # Return
+    /// None if the blob can't be a bubble: wrong size, or not a ring
+    fn score(&self, screen: &RgbImage, white: &PreparedTarget) -> Option<u8> {
+        let [x_min, y_min, x_max, y_max] = self.bounds;
+        let (width, height) = (x_max - x_min + 1, y_max - y_min + 1);
+
+        // Bubble size depends on the screen height
+        let expected = (screen.height() * 2 / 100)..=(screen.height() * 15 / 100);
+        if !expected.contains(&width.max(height)) {
+            return None;
+        }
+
+        // A bubble is a ring: its middle isn't white, but it is surrounded by white
+        let center = self.bounds_center();
+        let is_white = |x, y| white.matches(screen.get_pixel(x, y));
+        let surrounded = (x_min..center.x).any(|x| is_white(x, center.y))
+            && (center.x..=x_max).any(|x| is_white(x, center.y))
+            && (y_min..center.y).any(|y| is_white(center.x, y))
+            && (center.y..=y_max).any(|y| is_white(center.x, y));
+        if is_white(center.x, center.y) || !surrounded {
+            return None;
+        }
+
+        // A bubble is round, so its bounding box is a square
+        u8::try_from(width.min(height) * 100 / width.max(height)).ok()
+    }
+
+    fn bounds_center(&self) -> Point {
+        let [x_min, y_min, x_max, y_max] = self.bounds;
+        Point {
+            x: x_min.midpoint(x_max),
+            y: y_min.midpoint(y_max),
+        }
+    }
+
+    fn centroid(&self) -> Point {
+        let pixels = u64::from(self.pixels);
+        Point {
+            x: u32::try_from(self.sum_x / pixels).unwrap_or(u32::MAX),
+            y: u32::try_from(self.sum_y / pixels).unwrap_or(u32::MAX),
+        }
+    }
+}
+
+/// Find every white connected component of the region that could be a shake bubble,
+/// pixels inside `masks` are ignored
+///
+/// # Return
+/// Candidates, best score first
+#[must_use]
+pub fn search_bubbles(screen: &RgbImage, region: &Region, masks: &[Region]) -> Vec<Bubble> {
+    let white = ColorTarget {
+        color: Rgb([0xff, 0xff, 0xff]),
+        variation: 1,
+        matcher: Matcher::Rgb,
+    }
+    .prepare();
+    let is_white = |x, y| {
+        !masks.iter().any(|m| m.contains(&Point { x, y })) && white.matches(screen.get_pixel(x, y))
+    };
+
+    let [x_min, y_min, x_max, y_max] = region.corners();
+    let width = (x_max - x_min + 1) as usize;
+    let height = (y_max - y_min + 1) as usize;
+    let index = |x: u32, y: u32| (y - y_min) as usize * width + (x - x_min) as usize;
+
+    let mut visited = vec![false; width * height];
+    let mut blobs = Vec::new();
+
+    for y in y_min..=y_max {
+        for x in x_min..=x_max {
+            if visited[index(x, y)] || !is_white(x, y) {
+                continue;
+            }
+
+            // Flood fill the component
+            let mut blob = Blob {
+                pixels: 0,
+                sum_x: 0,
+                sum_y: 0,
+                bounds: [x, y, x, y],
+            };
+            let mut stack = vec![(x, y)];
+            visited[index(x, y)] = true;
+            while let Some((px, py)) = stack.pop() {
+                blob.pixels += 1;
+                blob.sum_x += u64::from(px);
+                blob.sum_y += u64::from(py);
+                blob.bounds = [
+                    blob.bounds[0].min(px),
+                    blob.bounds[1].min(py),
+                    blob.bounds[2].max(px),
+                    blob.bounds[3].max(py),
+                ];
+
+                let neighbours = [
+                    (px > x_min).then(|| (px - 1, py)),
+                    (px < x_max).then(|| (px + 1, py)),
+                    (py > y_min).then(|| (px, py - 1)),
+                    (py < y_max).then(|| (px, py + 1)),
+                ];
+                for (nx, ny) in neighbours.into_iter().flatten() {
+                    if !visited[index(nx, ny)] && is_white(nx, ny) {
+                        visited[index(nx, ny)] = true;
+                        stack.push((nx, ny));
+                    }
+                }
+            }
+
+            // Ignore noise
+            if blob.pixels >= 20 {
+                blobs.push(blob);
+            }
+        }
+    }
+
+    let mut bubbles = blobs
+        .iter()
+        .filter_map(|blob| {
+            let [x_min, y_min, x_max, y_max] = blob.bounds;
+            Some(Bubble {
+                center: blob.centroid(),
+                bounds: Region {
+                    point1: Point { x: x_min, y: y_min },
+                    point2: Point { x: x_max, y: y_max },
+                },
+                score: blob.score(screen, &white)?,
+            })
+        })
+        .collect::<Vec<_>>();
+    bubbles.sort_unstable_by_key(|b| u8::MAX - b.score);
+
+    bubbles
+}
+
+/// Remember the bubbles we already hit, so we don't click them again while they fade out
+#[derive(Default)]
+pub struct BubbleTracker {
+    hits: Vec<(Region, Instant)>,
+}
+
+impl BubbleTracker {
+    /// How long a bubble can stay on screen after being hit
+    const MEMORY: Duration = Duration::from_secs(1);
+
+    /// Check if the bubble is one we already hit
+    #[must_use]
+    pub fn already_hit(&self, bubble: &Bubble) -> bool {
+        self.hits
+            .iter()
+            .any(|(bounds, time)| time.elapsed() < Self::MEMORY && bounds.contains(&bubble.center))
+    }
+
+    /// Remember a bubble we just hit
+    pub fn hit(&mut self, bubble: &Bubble) {
+        self.hits.retain(|(_, time)| time.elapsed() < Self::MEMORY);
+        self.hits.push((bubble.bounds.clone(), Instant::now()));
+    }
+
+    /// Forget every bubble
+    pub fn clear(&mut self) {
+        self.hits.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REGION: Region = Region {
+        point1: Point { x: 0, y: 0 },
+        point2: Point { x: 399, y: 299 },
+    };
+
+    /// Tells if a pixel is part of the shape, from its offset to the shape center
+    type Shape = fn(i32, i32) -> bool;
+
+    /// Dark screen with white shapes
+    fn screen(shapes: &[(Point, Shape)]) -> RgbImage {
+        RgbImage::from_fn(400, 300, |x, y| {
+            let white = shapes.iter().any(|(center, shape)| {
+                shape(
+                    x.cast_signed() - center.x.cast_signed(),
+                    y.cast_signed() - center.y.cast_signed(),
+                )
+            });
+            if white {
+                Rgb([0xff, 0xff, 0xff])
+            } else {
+                Rgb([0x20, 0x40, 0x60])
+            }
+        })
+    }
+
+    fn ring(dx: i32, dy: i32) -> bool {
+        (14 * 14..=18 * 18).contains(&(dx * dx + dy * dy))
+    }
+
+    fn disc(dx: i32, dy: i32) -> bool {
+        dx * dx + dy * dy <= 18 * 18
+    }
+
+    fn huge_ring(dx: i32, dy: i32) -> bool {
+        (60 * 60..=64 * 64).contains(&(dx * dx + dy * dy))
+    }
+
+    fn arc(dx: i32, dy: i32) -> bool {
+        dy < 0 && ring(dx, dy)
+    }
+
+    #[test]
+    fn finds_the_ring() {
+        let bubbles = search_bubbles(&screen(&[(Point { x: 100, y: 100 }, ring)]), &REGION, &[]);
+
+        assert_eq!(bubbles.len(), 1);
+        assert!(bubbles[0].score >= MIN_BUBBLE_SCORE);
+        assert!(bubbles[0].center.x.abs_diff(100) <= 1);
+    }
+
+    #[test]
+    fn rejects_other_shapes() {
+        // Filled, too large, and open shapes aren't bubbles, even when square enough
+        let shapes = [
+            (Point { x: 60, y: 60 }, disc as Shape),
+            (Point { x: 250, y: 150 }, huge_ring),
+            (Point { x: 60, y: 220 }, arc),
+        ];
+
+        assert!(search_bubbles(&screen(&shapes), &REGION, &[]).is_empty());
+    }
+}
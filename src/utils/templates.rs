@@ -0,0 +1,178 @@
+use image::{
+    GrayImage, RgbImage,
+    imageops::{self, FilterType},
+};
+
+use crate::utils::{
+    geometry::{Point, Region},
+    helpers::BadCast,
+};
+
+/// Screen height the reference images have been captured at
+const REFERENCE_HEIGHT: u32 = 1080;
+
+/// Both the screen and the references are shrunk by this factor before matching
+const DOWNSCALE: u32 = 2;
+
+/// Scales tried on top of the screen height ratio, in percent
+const SCALES: [u32; 5] = [75, 90, 100, 115, 130];
+
+/// UI elements we have a reference image of
+#[derive(Clone, Copy, Debug)]
+pub enum Template {
+    /// Roblox button, at the top left of the game
+    RobloxButton,
+    /// Reconnect button of the disconnection popup
+    Reconnect,
+    /// Leave button of the disconnection popup
+    Leave,
+}
+
+impl Template {
+    /// PNG bundled in the binary
+    fn bytes(self) -> &'static [u8] {
+        match self {
+            Self::RobloxButton => include_bytes!("../../assets/templates/roblox_button.png"),
+            Self::Reconnect => include_bytes!("../../assets/templates/reconnect.png"),
+            Self::Leave => include_bytes!("../../assets/templates/leave.png"),
+        }
+    }
+
+    /// Grayscale reference image
+    ///
+    /// # Panics
+    /// If the bundled image is invalid
+    #[must_use]
+    pub fn image(self) -> GrayImage {
+        image::load_from_memory(self.bytes())
+            .expect("Bundled template is invalid")
+            .to_luma8()
+    }
+}
+
+/// Where a template has been found
+pub struct TemplateMatch {
+    pub center: Point,
+    pub bounds: Region,
+    /// Normalized cross-correlation, in percent
+    pub score: u8,
+}
+
+/// Normalized cross-correlation of `template` with `img`, from the top left corner `(x, y)`
+#[must_use]
+pub fn ncc(img: &GrayImage, template: &GrayImage, (x, y): (u32, u32)) -> f32 {
+    let (width, height) = template.dimensions();
+    let count = i32::try_from(width * height).unwrap_or(i32::MAX).bad_cast();
+
+    let (mut img_sum, mut template_sum) = (0., 0.);
+    let (mut img_squares, mut template_squares, mut products) = (0., 0., 0.);
+    for ty in 0..height {
+        for tx in 0..width {
+            let pixel = f32::from(img.get_pixel(x + tx, y + ty)[0]);
+            let reference = f32::from(template.get_pixel(tx, ty)[0]);
+            img_sum += pixel;
+            template_sum += reference;
+            img_squares += pixel * pixel;
+            template_squares += reference * reference;
+            products += pixel * reference;
+        }
+    }
+
+    let covariance = products - img_sum * template_sum / count;
+    let variance = (img_squares - img_sum * img_sum / count)
+        * (template_squares - template_sum * template_sum / count);
+    if variance <= f32::EPSILON {
+        // Flat area, nothing to correlate with
+        return 0.;
+    }
+
+    covariance / variance.sqrt()
+}
+
+impl Region {
+    /// Search a template in the region, trying multiple scales
+    ///
+    /// # Return
+    /// Best match scoring at least `min_score`
+    #[must_use]
+    pub fn search_template(
+        &self,
+        screen: &RgbImage,
+        template: Template,
+        min_score: u8,
+    ) -> Option<TemplateMatch> {
+        let [x_min, y_min, x_max, y_max] = self.corners();
+        let area = imageops::grayscale(&*imageops::crop_imm(
+            screen,
+            x_min,
+            y_min,
+            x_max - x_min + 1,
+            y_max - y_min + 1,
+        ));
+        let area = imageops::resize(
+            &area,
+            (area.width() / DOWNSCALE).max(1),
+            (area.height() / DOWNSCALE).max(1),
+            FilterType::Triangle,
+        );
+        let reference = template.image();
+
+        let found = SCALES
+            .iter()
+            .filter_map(|scale| {
+                let size =
+                    |s: u32| s * scale * screen.height() / (100 * REFERENCE_HEIGHT * DOWNSCALE);
+                let (width, height) = (size(reference.width()), size(reference.height()));
+                if width < 4 || height < 4 || width > area.width() || height > area.height() {
+                    return None;
+                }
+
+                let scaled = imageops::resize(&reference, width, height, FilterType::Triangle);
+                (0..=area.height() - height)
+                    .flat_map(|y| (0..=area.width() - width).map(move |x| (x, y)))
+                    .map(|(x, y)| (x, y, width, height, ncc(&area, &scaled, (x, y))))
+                    .max_by(|a, b| a.4.total_cmp(&b.4))
+            })
+            .max_by(|a, b| a.4.total_cmp(&b.4))
+            .and_then(|(x, y, width, height, score)| {
+                let score = u8::try_from((score * 100.).bad_cast()).ok()?;
+                (score >= min_score).then(|| {
+                    let bounds = Region {
+                        point1: Point {
+                            x: x_min + x * DOWNSCALE,
+                            y: y_min + y * DOWNSCALE,
+                        },
+                        point2: Point {
+                            x: x_min + (x + width) * DOWNSCALE - 1,
+                            y: y_min + (y + height) * DOWNSCALE - 1,
+                        },
+                    };
+                    let [l, t, r, b] = bounds.corners();
+                    TemplateMatch {
+                        center: Point {
+                            x: l.midpoint(r),
+                            y: t.midpoint(b),
+                        },
+                        bounds,
+                        score,
+                    }
+                })
+            });
+
+        #[cfg(feature = "imageproc")]
+        {
+            if let Some(m) = found.as_ref() {
+                use crate::utils::debug::Drawable;
+                use std::sync::Arc;
+
+                m.bounds.clone().draw_async(
+                    Arc::new(screen.clone()),
+                    format!("template_{template:?}.png"),
+                    true,
+                );
+            }
+        }
+
+        found
+    }
+}
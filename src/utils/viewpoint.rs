@@ -0,0 +1,168 @@
+use image::{Rgb, RgbImage};
+
+use crate::utils::{
+    colors::{ColorTarget, Matcher},
+    geometry::{Point, Region},
+};
+
+/// Usual water color, in daylight
+pub const WATER: Rgb<u8> = Rgb([0x2b, 0x6c, 0x9e]);
+
+/// Step between two sampled pixels
+const SAMPLING: usize = 6;
+
+/// What the camera should show to fish comfortably, in percent
+pub struct ViewProfile {
+    /// Water color, only its hue and saturation matter
+    pub water: Rgb<u8>,
+    /// Minimum water in the view
+    pub min_water: u8,
+    /// Maximum non-water around the center of the screen, where the character stands
+    pub max_occlusion: u8,
+    /// Maximum noise where the minigame appears
+    pub max_mini_game_noise: u8,
+}
+
+impl Default for ViewProfile {
+    fn default() -> Self {
+        Self {
+            water: WATER,
+            min_water: 50,
+            max_occlusion: 35,
+            max_mini_game_noise: 20,
+        }
+    }
+}
+
+/// What is wrong with the view
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViewIssue {
+    /// The character is in the way, the camera is too close
+    Occluded,
+    /// Something covers the minigame area
+    MiniGameCovered,
+    /// The camera doesn't look down enough
+    NotEnoughWater,
+}
+
+/// Measures of the camera view, in percent
+pub struct View {
+    pub water: u8,
+    pub occlusion: u8,
+    pub mini_game_noise: u8,
+}
+
+/// Percentage of the sampled pixels of the region matching the predicate
+fn ratio(screen: &RgbImage, region: &Region, predicate: impl Fn(&Rgb<u8>) -> bool) -> u8 {
+    let [x_min, y_min, x_max, y_max] = region.corners();
+    let (matching, total) = (y_min..=y_max)
+        .step_by(SAMPLING)
+        .flat_map(|y| (x_min..=x_max).step_by(SAMPLING).map(move |x| (x, y)))
+        .fold((0, 0), |(matching, total), (x, y)| {
+            (
+                matching + usize::from(predicate(screen.get_pixel(x, y))),
+                total + 1,
+            )
+        });
+
+    u8::try_from(matching * 100 / total.max(1)).unwrap_or(100)
+}
+
+impl View {
+    /// Measure the view, before any minigame shows up
+    #[must_use]
+    pub fn measure(screen: &RgbImage, mini_game: &Region, profile: &ViewProfile) -> Self {
+        let (width, height) = screen.dimensions();
+        let area = |x1, y1, x2, y2| Region {
+            point1: Point {
+                x: width * x1 / 100,
+                y: height * y1 / 100,
+            },
+            point2: Point {
+                x: width * x2 / 100,
+                y: height * y2 / 100,
+            },
+        };
+
+        // Without the HUD, at the top and the bottom of the screen
+        let water_target = ColorTarget {
+            color: profile.water,
+            variation: 35,
+            matcher: Matcher::Hsv,
+        }
+        .prepare();
+        let water = ratio(screen, &area(0, 20, 100, 75), |p| water_target.matches(p));
+        let occlusion = ratio(screen, &area(40, 35, 60, 75), |p| !water_target.matches(p));
+
+        // The minigame area should be plain
+        let [x_min, y_min, x_max, y_max] = mini_game.corners();
+        let mut brightness = (y_min..=y_max)
+            .step_by(SAMPLING)
+            .flat_map(|y| (x_min..=x_max).step_by(SAMPLING).map(move |x| (x, y)))
+            .map(|(x, y)| ColorTarget::brightness(screen.get_pixel(x, y)))
+            .collect::<Vec<_>>();
+        brightness.sort_unstable();
+        let median = brightness.get(brightness.len() / 2).copied().unwrap_or(0);
+        let mini_game_noise = ratio(screen, mini_game, |p| {
+            (ColorTarget::brightness(p) - median).abs() > 40
+        });
+
+        Self {
+            water,
+            occlusion,
+            mini_game_noise,
+        }
+    }
+
+    /// Returns the most important issue of the view, if any
+    #[must_use]
+    pub fn issue(&self, profile: &ViewProfile) -> Option<ViewIssue> {
+        if self.occlusion > profile.max_occlusion {
+            Some(ViewIssue::Occluded)
+        } else if self.mini_game_noise > profile.max_mini_game_noise {
+            Some(ViewIssue::MiniGameCovered)
+        } else if self.water < profile.min_water {
+            Some(ViewIssue::NotEnoughWater)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view(water: u8, occlusion: u8, mini_game_noise: u8) -> View {
+        View {
+            water,
+            occlusion,
+            mini_game_noise,
+        }
+    }
+
+    #[test]
+    fn accepts_a_clear_view() {
+        let profile = ViewProfile::default();
+
+        assert_eq!(view(80, 10, 5).issue(&profile), None);
+        // Limits are included
+        assert_eq!(view(50, 35, 20).issue(&profile), None);
+    }
+
+    #[test]
+    fn reports_the_most_important_issue() {
+        let profile = ViewProfile::default();
+
+        // Zooming out may fix everything else
+        assert_eq!(view(10, 60, 50).issue(&profile), Some(ViewIssue::Occluded));
+        assert_eq!(
+            view(10, 20, 50).issue(&profile),
+            Some(ViewIssue::MiniGameCovered)
+        );
+        assert_eq!(
+            view(10, 20, 5).issue(&profile),
+            Some(ViewIssue::NotEnoughWater)
+        );
+    }
+}
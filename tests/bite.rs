@@ -0,0 +1,79 @@
+use fischy::utils::{fishing::MiniGame, geometry::Dimensions};
+use image::RgbImage;
+
+fn fixture(bytes: &[u8]) -> RgbImage {
+    image::load_from_memory(bytes)
+        .expect("Invalid fixture")
+        .to_rgb8()
+}
+
+fn mini_game() -> MiniGame {
+    Dimensions {
+        width: 1280,
+        height: 720,
+    }
+    .calculate_mini_game_region()
+}
+
+#[test]
+fn detects_the_bite() {
+    let bite = fixture(include_bytes!("fixtures/bite.png"));
+    let mut mini_game = mini_game();
+
+    // A single frame isn't enough
+    assert!(!mini_game.bite_detected(&bite));
+    assert!(mini_game.bite_detected(&bite));
+}
+
+#[test]
+fn ignores_the_water() {
+    for water in [
+        fixture(include_bytes!("fixtures/day_water.png")),
+        fixture(include_bytes!("fixtures/night_water.png")),
+    ] {
+        let mut mini_game = mini_game();
+        for _ in 0..10 {
+            assert!(!mini_game.bite_detected(&water));
+        }
+        assert_eq!(mini_game.bite_confidence(), 0);
+    }
+}
+
+#[test]
+fn forgets_the_previous_bite() {
+    let bite = fixture(include_bytes!("fixtures/bite.png"));
+    let water = fixture(include_bytes!("fixtures/night_water.png"));
+    let mut mini_game = mini_game();
+    assert!((0..5).any(|_| mini_game.bite_detected(&bite)));
+
+    mini_game.reset_bite();
+    assert!(!mini_game.bite_detected(&water));
+}
+
+#[test]
+fn waits_for_the_bar_to_appear() {
+    let fading = fixture(include_bytes!("fixtures/bite_fading.png"));
+    let bite = fixture(include_bytes!("fixtures/bite.png"));
+    let mut mini_game = mini_game();
+
+    // The bar fading in isn't readable yet
+    assert!(!mini_game.bite_detected(&fading));
+    assert_eq!(mini_game.bite_confidence(), 0);
+
+    assert!(!mini_game.bite_detected(&bite));
+    assert!(mini_game.bite_detected(&bite));
+}
+
+#[test]
+fn follows_the_moving_bar() {
+    let frames = [
+        fixture(include_bytes!("fixtures/bite.png")),
+        fixture(include_bytes!("fixtures/bite_moving.png")),
+    ];
+    let mut mini_game = mini_game();
+
+    assert!(!mini_game.bite_detected(&frames[0]));
+    for i in 1..10 {
+        assert!(mini_game.bite_detected(&frames[i % 2]));
+    }
+}
@@ -0,0 +1,56 @@
+use fischy::utils::{
+    checks::SERVER_OFFLINE,
+    geometry::{Point, Region},
+    viewpoint::{View, ViewProfile},
+};
+use image::RgbImage;
+
+fn fixture(bytes: &[u8]) -> RgbImage {
+    image::load_from_memory(bytes)
+        .expect("Invalid fixture")
+        .to_rgb8()
+}
+
+fn mini_game() -> Region {
+    Region {
+        point1: Point { x: 360, y: 590 },
+        point2: Point { x: 920, y: 630 },
+    }
+}
+
+#[test]
+fn finds_the_water_by_day_night_and_rain() {
+    for screen in [
+        fixture(include_bytes!("fixtures/day_water.png")),
+        fixture(include_bytes!("fixtures/night_water.png")),
+        fixture(include_bytes!("fixtures/rain_water.png")),
+    ] {
+        let profile = ViewProfile::default();
+        let view = View::measure(&screen, &mini_game(), &profile);
+        assert!(view.water >= profile.min_water);
+        // The character stands in the middle, it isn't water
+        assert!(view.occlusion > 0);
+    }
+}
+
+#[test]
+fn finds_the_popup_by_day_and_night() {
+    for screen in [
+        fixture(include_bytes!("fixtures/disconnected.png")),
+        fixture(include_bytes!("fixtures/disconnected_night.png")),
+    ] {
+        assert!(SERVER_OFFLINE.detect(&screen, None).is_some());
+    }
+}
+
+#[test]
+fn ignores_grey_water() {
+    // Fog at night is almost the grey of the popup
+    for screen in [
+        fixture(include_bytes!("fixtures/fog_night_water.png")),
+        fixture(include_bytes!("fixtures/rain_water.png")),
+        fixture(include_bytes!("fixtures/night_water.png")),
+    ] {
+        assert!(SERVER_OFFLINE.detect(&screen, None).is_none());
+    }
+}
@@ -0,0 +1,108 @@
+use fischy::utils::{
+    checks::DAILY_REWARD,
+    geometry::{Point, Region},
+    ocr::{learn_glyphs, read_text},
+};
+use image::RgbImage;
+
+fn fixture(bytes: &[u8]) -> RgbImage {
+    image::load_from_memory(bytes)
+        .expect("Invalid fixture")
+        .to_rgb8()
+}
+
+fn whole(crop: &RgbImage) -> Region {
+    Region {
+        point1: Point { x: 0, y: 0 },
+        point2: Point {
+            x: crop.width() - 1,
+            y: crop.height() - 1,
+        },
+    }
+}
+
+#[test]
+fn reads_the_crops() {
+    for (crop, expected) in [
+        (
+            include_bytes!("fixtures/text_level_up.png").as_slice(),
+            "Level Up!",
+        ),
+        (
+            include_bytes!("fixtures/text_inventory_full.png"),
+            "Inventory Full",
+        ),
+        (
+            include_bytes!("fixtures/text_idle.png"),
+            "You have been idle",
+        ),
+        (
+            include_bytes!("fixtures/text_shiny_tuna.png"),
+            "Shiny Tuna (12.5kg)",
+        ),
+        (
+            include_bytes!("fixtures/text_abyssal_anglerfish.png"),
+            "Abyssal Anglerfish (48.2kg)",
+        ),
+    ] {
+        let crop = fixture(crop);
+        let text = read_text(&crop, &whole(&crop)).expect("No text");
+
+        assert_eq!(text.text, expected);
+        assert!(text.contains(&expected.to_lowercase()));
+    }
+}
+
+#[test]
+fn learns_the_glyphs_of_a_crop() {
+    let crop = fixture(include_bytes!("fixtures/text_level_up.png"));
+
+    let glyphs = learn_glyphs(&crop, &whole(&crop), "Level Up!").expect("No glyphs");
+    for character in ['L', 'e', 'v', 'l', 'U', 'p', '!'] {
+        assert!(glyphs.contains(&format!("\n{character}\n")));
+    }
+
+    // Each glyph needs its character
+    assert!(learn_glyphs(&crop, &whole(&crop), "Level Up").is_err());
+}
+
+#[test]
+fn skips_areas_without_text() {
+    // Where the popups show up
+    let area = Region {
+        point1: Point { x: 384, y: 72 },
+        point2: Point { x: 896, y: 240 },
+    };
+
+    for screen in [
+        fixture(include_bytes!("fixtures/day_water.png")),
+        fixture(include_bytes!("fixtures/night_water.png")),
+        fixture(include_bytes!("fixtures/fog_night_water.png")),
+    ] {
+        assert!(read_text(&screen, &area).is_none());
+    }
+
+    // Rain streaks are sharp enough to be read, without finding any word
+    let rain = fixture(include_bytes!("fixtures/rain_water.png"));
+    assert!(read_text(&rain, &area).is_none_or(|text| {
+        !["level", "full", "event", "claim"]
+            .iter()
+            .any(|word| text.contains(word))
+    }));
+}
+
+#[test]
+fn finds_the_claim_button() {
+    let screen = fixture(include_bytes!("fixtures/daily_reward.png"));
+    let button = Region {
+        point1: Point { x: 550, y: 482 },
+        point2: Point { x: 730, y: 526 },
+    };
+
+    // Clicked where the button was read
+    let found = DAILY_REWARD.detect(&screen, None).expect("No claim button");
+    assert!(button.contains(&found));
+
+    let water = fixture(include_bytes!("fixtures/day_water.png"));
+    assert!(DAILY_REWARD.detect(&water, None).is_none());
+}
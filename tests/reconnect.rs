@@ -0,0 +1,33 @@
+use fischy::utils::{checks::SERVER_OFFLINE, geometry::Point, reconnect::DisconnectPopup};
+use image::RgbImage;
+
+fn fixture(bytes: &[u8]) -> RgbImage {
+    image::load_from_memory(bytes)
+        .expect("Invalid fixture")
+        .to_rgb8()
+}
+
+#[test]
+fn finds_the_buttons() {
+    let screen = fixture(include_bytes!("fixtures/disconnected.png"));
+    assert!(SERVER_OFFLINE.detect(&screen, None).is_some());
+
+    let popup = DisconnectPopup::search(&screen).expect("No popup");
+    assert!(popup.reconnect.bounds.contains(&Point { x: 780, y: 455 }));
+
+    // Both buttons look alike, they must not be mistaken for each other
+    let leave = popup.leave.expect("No leave button");
+    assert!(leave.bounds.contains(&Point { x: 500, y: 455 }));
+}
+
+#[test]
+fn ignores_the_game() {
+    for screen in [
+        fixture(include_bytes!("fixtures/day_water.png")),
+        fixture(include_bytes!("fixtures/night_water.png")),
+        fixture(include_bytes!("fixtures/bite.png")),
+    ] {
+        assert!(SERVER_OFFLINE.detect(&screen, None).is_none());
+        assert!(DisconnectPopup::search(&screen).is_none());
+    }
+}